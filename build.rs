@@ -0,0 +1,7 @@
+fn main() {
+    // target triple used to pick release artifacts when self updating.
+    println!(
+        "cargo:rustc-env=TARGET={}",
+        std::env::var("TARGET").unwrap()
+    );
+}
@@ -0,0 +1,387 @@
+//! DFU helpers not covered by `dfu-nusb`.
+
+use crate::error::{Error, Result};
+use dfu_core::{asynchronous::DfuAsyncIo, DfuProtocol, State};
+use dfu_nusb::DfuNusb;
+use futures::AsyncRead;
+use nusb::DeviceInfo;
+use std::{
+    fmt::Display,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tracing::{debug, instrument, trace};
+
+/// DFU interface class code.
+pub const DFU_CLASS: u8 = 0xFE;
+/// DFU interface subclass code.
+pub const DFU_SUBCLASS: u8 = 0x01;
+/// DFU interface protocol code when the device is in DFU mode.
+pub const DFU_PROTOCOL_DFU_MODE: u8 = 0x02;
+
+const REQUEST_TYPE_OUT: u8 = 0b0010_0001;
+const REQUEST_TYPE_IN: u8 = 0b1010_0001;
+const DFU_DNLOAD: u8 = 1;
+const DFU_UPLOAD: u8 = 2;
+const DFU_GETSTATUS: u8 = 3;
+const DFU_CLRSTATUS: u8 = 4;
+const DFU_ABORT: u8 = 6;
+/// DfuSe command to set the address pointer.
+const DFUSE_SET_ADDRESS: u8 = 0x21;
+/// Minimum length of the DFU file suffix.
+const SUFFIX_LEN: usize = 16;
+
+/// Whether the device is enumerated in DFU mode rather than run-time mode.
+pub fn in_dfu_mode(dev: &DeviceInfo) -> bool {
+    dev.interfaces().any(|i| {
+        i.class() == DFU_CLASS
+            && i.subclass() == DFU_SUBCLASS
+            && i.protocol() == DFU_PROTOCOL_DFU_MODE
+    })
+}
+
+/// Open the DFU interface of a device.
+#[instrument(level = "debug", skip_all, fields(serial = dev.serial_number()))]
+pub fn open(dev: &DeviceInfo) -> Result<DfuNusb> {
+    let device = dev.open().map_err(unreachable)?;
+
+    let number = device
+        .active_configuration()
+        .map_err(unreachable)?
+        .interface_alt_settings()
+        .find(|alt| alt.class() == DFU_CLASS && alt.subclass() == DFU_SUBCLASS)
+        .map(|alt| alt.interface_number())
+        .ok_or_else(|| {
+            Error::Unsupported(
+                "Device does not have a DFU interface.".to_string(),
+            )
+        })?;
+
+    let interface = device.claim_interface(number).map_err(unreachable)?;
+    debug!(interface = number, "claimed DFU interface");
+
+    DfuNusb::open(device, interface, 0).map_err(failed)
+}
+
+/// Download a firmware image to the device.
+///
+/// `progress` is called with the block number and total number of blocks as
+/// each block is read for sending. Returns the device for further requests.
+#[instrument(level = "debug", skip_all, fields(len = image.len()))]
+pub async fn download(
+    dfu: DfuNusb,
+    image: &[u8],
+    progress: impl FnMut(u32, u32) + Unpin,
+) -> Result<DfuNusb> {
+    let transfer_size =
+        (dfu.functional_descriptor().transfer_size as usize).max(1);
+    let length = u32::try_from(image.len()).map_err(|_| {
+        Error::Firmware("Firmware image is too large.".to_string())
+    })?;
+
+    let reader = ProgressReader {
+        image,
+        sent: 0,
+        transfer_size,
+        progress,
+    };
+    let mut dfu = dfu.into_async_dfu();
+    dfu.download(reader, length).await.map_err(failed)?;
+
+    Ok(dfu.into_inner())
+}
+
+/// Reader over an image that reports which block is being read.
+struct ProgressReader<'a, F> {
+    image: &'a [u8],
+    sent: usize,
+    transfer_size: usize,
+    progress: F,
+}
+
+impl<F: FnMut(u32, u32) + Unpin> AsyncRead for ProgressReader<'_, F> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        let n = buf.len().min(this.image.len() - this.sent);
+
+        if n > 0 {
+            let total = this.image.len().div_ceil(this.transfer_size);
+            (this.progress)(
+                (this.sent / this.transfer_size) as u32,
+                total as u32,
+            );
+        }
+
+        buf[..n].copy_from_slice(&this.image[this.sent..this.sent + n]);
+        this.sent += n;
+
+        Poll::Ready(Ok(n))
+    }
+}
+
+/// Read the firmware image from the device.
+///
+/// Reads until the device signals the end of the image with a short block, or
+/// until `length` bytes have been read.
+#[instrument(level = "debug", skip(dfu))]
+pub async fn upload(dfu: &DfuNusb, length: Option<usize>) -> Result<Vec<u8>> {
+    let transfer_size = dfu.functional_descriptor().transfer_size as usize;
+
+    if !dfu.functional_descriptor().can_upload {
+        return Err(Error::Unsupported(
+            "Device does not support firmware readback.".to_string(),
+        ));
+    }
+
+    reset_to_idle(dfu).await?;
+
+    // DfuSe devices read from the address pointer starting at block 2.
+    let first_block = match dfu.protocol() {
+        DfuProtocol::Dfu => 0,
+        DfuProtocol::Dfuse { address, .. } => {
+            let mut command = [DFUSE_SET_ADDRESS, 0, 0, 0, 0];
+            command[1..].copy_from_slice(&address.to_le_bytes());
+            dfu.write_control(REQUEST_TYPE_OUT, DFU_DNLOAD, 0, &command)
+                .await
+                .map_err(failed)?;
+            wait_while_busy(dfu).await?;
+            dfu.write_control(REQUEST_TYPE_OUT, DFU_ABORT, 0, &[])
+                .await
+                .map_err(failed)?;
+            2
+        }
+    };
+
+    let mut image = Vec::new();
+    let mut block = vec![0; transfer_size];
+
+    for block_num in first_block.. {
+        let n = dfu
+            .read_control(REQUEST_TYPE_IN, DFU_UPLOAD, block_num, &mut block)
+            .await
+            .map_err(failed)?;
+        trace!(block = block_num, len = n, "uploaded block");
+        image.extend_from_slice(&block[..n]);
+
+        if let Some(length) = length {
+            if image.len() >= length {
+                image.truncate(length);
+                break;
+            }
+        }
+
+        if n < transfer_size {
+            break;
+        }
+    }
+
+    dfu.write_control(REQUEST_TYPE_OUT, DFU_ABORT, 0, &[])
+        .await
+        .map_err(failed)?;
+
+    Ok(image)
+}
+
+/// Check the firmware on the device matches an image by reading it back.
+pub async fn verify(dfu: &DfuNusb, image: &[u8]) -> Result<()> {
+    let readback = upload(dfu, Some(image.len())).await?;
+
+    if readback != image {
+        let offset = readback.iter().zip(image).position(|(a, b)| a != b);
+        debug!(?offset, len = readback.len(), "readback mismatch");
+        return Err(Error::Firmware(
+            "Firmware read back from the device does not match the flashed image."
+                .to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Bring the device back to the `dfuIDLE` state.
+async fn reset_to_idle(dfu: &DfuNusb) -> Result<()> {
+    match get_status(dfu).await?.0 {
+        State::DfuIdle => {}
+        State::DfuError => {
+            dfu.write_control(REQUEST_TYPE_OUT, DFU_CLRSTATUS, 0, &[])
+                .await
+                .map_err(failed)?;
+        }
+        _ => {
+            dfu.write_control(REQUEST_TYPE_OUT, DFU_ABORT, 0, &[])
+                .await
+                .map_err(failed)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Poll the device status until it is no longer busy.
+async fn wait_while_busy(dfu: &DfuNusb) -> Result<()> {
+    loop {
+        let (state, poll_timeout) = get_status(dfu).await?;
+        match state {
+            State::DfuDnbusy | State::DfuDnloadSync => {
+                tokio::time::sleep(poll_timeout).await
+            }
+            State::DfuError => {
+                return Err(Error::Firmware(
+                    "Device reported a DFU error.".to_string(),
+                ))
+            }
+            _ => return Ok(()),
+        }
+    }
+}
+
+/// Get the device state and requested poll timeout.
+async fn get_status(dfu: &DfuNusb) -> Result<(State, Duration)> {
+    let mut status = [0; 6];
+    let n = dfu
+        .read_control(REQUEST_TYPE_IN, DFU_GETSTATUS, 0, &mut status)
+        .await
+        .map_err(failed)?;
+
+    if n < status.len() {
+        return Err(Error::Firmware(
+            "Device returned a short DFU status.".to_string(),
+        ));
+    }
+
+    let poll_timeout = u32::from_le_bytes([status[1], status[2], status[3], 0]);
+
+    let state = State::from(status[4]);
+    debug!(%state, status = status[0], poll_timeout, "DFU status");
+
+    Ok((state, Duration::from_millis(poll_timeout as u64)))
+}
+
+/// Split the DFU suffix off a firmware file.
+///
+/// Returns the image to download and the `bcdDevice` firmware version from
+/// the suffix, if the file has a suffix that specifies one. Files without a
+/// suffix are returned whole.
+pub fn split_suffix(file: &[u8]) -> Result<(&[u8], Option<u16>)> {
+    let Some(suffix) = file.len().checked_sub(SUFFIX_LEN).map(|i| &file[i..])
+    else {
+        return Ok((file, None));
+    };
+
+    let len = suffix[11] as usize;
+    if &suffix[8..11] != b"UFD" || len < SUFFIX_LEN || len > file.len() {
+        return Ok((file, None));
+    }
+
+    let (checked, crc) = file.split_at(file.len() - 4);
+    if crc32(checked).to_le_bytes() != crc {
+        return Err(Error::Firmware(
+            "Firmware file suffix checksum does not match, the file may be corrupt."
+                .to_string(),
+        ));
+    }
+
+    let version = u16::from_le_bytes([suffix[0], suffix[1]]);
+    Ok((
+        &file[..file.len() - len],
+        (version != 0xFFFF).then_some(version),
+    ))
+}
+
+/// Error for a device that could not be opened.
+fn unreachable(err: impl Display) -> Error {
+    Error::Unreachable(format!("Could not open the device: {}.", err))
+}
+
+/// Error for a failed DFU request.
+fn failed(err: impl Display) -> Error {
+    Error::Firmware(format!("DFU request failed: {}.", err))
+}
+
+/// CRC-32 as used by the DFU file suffix, without the final inversion.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFF_u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Append a DFU suffix as written by `dfu-suffix`.
+    fn with_suffix(image: &[u8], version: u16) -> Vec<u8> {
+        let mut file = image.to_vec();
+        file.extend(version.to_le_bytes());
+        file.extend(0x2323_u16.to_le_bytes());
+        file.extend(0x1209_u16.to_le_bytes());
+        file.extend(0x0100_u16.to_le_bytes());
+        file.extend(b"UFD");
+        file.push(SUFFIX_LEN as u8);
+        let crc = crc32(&file);
+        file.extend(crc.to_le_bytes());
+        file
+    }
+
+    #[test]
+    fn download_progress() {
+        use futures::AsyncReadExt;
+
+        let image = [0x55; 10];
+        let mut calls = Vec::new();
+        let mut reader = ProgressReader {
+            image: &image,
+            sent: 0,
+            transfer_size: 4,
+            progress: |block, total| calls.push((block, total)),
+        };
+
+        let mut read = Vec::new();
+        let mut buf = [0; 4];
+        futures::executor::block_on(async {
+            loop {
+                match reader.read(&mut buf).await.unwrap() {
+                    0 => break,
+                    n => read.extend_from_slice(&buf[..n]),
+                }
+            }
+        });
+
+        assert_eq!(read, image);
+        assert_eq!(calls, [(0, 3), (1, 3), (2, 3)]);
+    }
+
+    #[test]
+    fn suffix() {
+        let image = [0xAA; 64];
+
+        let file = with_suffix(&image, 0x0120);
+        let (split, version) = split_suffix(&file).unwrap();
+        assert_eq!(split, image);
+        assert_eq!(version, Some(0x0120));
+
+        let file = with_suffix(&image, 0xFFFF);
+        assert_eq!(split_suffix(&file).unwrap().1, None);
+
+        let mut corrupt = with_suffix(&image, 0x0120);
+        corrupt[0] = 0;
+        assert!(split_suffix(&corrupt).is_err());
+
+        assert_eq!(split_suffix(&image).unwrap(), (&image[..], None));
+        assert_eq!(split_suffix(&[1, 2]).unwrap(), (&[1, 2][..], None));
+    }
+}
@@ -1,41 +1,252 @@
-mod list;
-mod update;
+//! Adapter devices.
 
-use clap::{Parser, Subcommand};
-use std::{net::IpAddr, path::PathBuf};
+pub mod dfu;
+pub mod release;
 
-#[derive(Subcommand)]
-pub enum Commands {
-    /// List devices.
-    List,
-    /// Update firmware
-    Update(UpdateOptions),
+use crate::error::{Error, Result};
+use dfu::in_dfu_mode;
+use nusb::DeviceInfo;
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+/// How long to wait for the device to re-enumerate after an update.
+const REENUMERATE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// List attached Adapter devices, including those in bootloader mode.
+pub fn list_devices() -> Result<Vec<DeviceInfo>> {
+    let devices = nusb::list_devices().map_err(|err| {
+        Error::Unreachable(format!("Could not list USB devices: {}.", err))
+    })?;
+
+    Ok(devices.filter(is_adapter).collect())
+}
+
+/// USB vendor ID of Adapters.
+const VENDOR_ID: u16 = 0x1209;
+
+/// USB product ID of Adapters, in both the application and the bootloader.
+const PRODUCT_ID: u16 = 0x2323;
+
+/// Manufacturer string reported by the application firmware.
+const MANUFACTURER: &str = "Universal Machine Intelligence";
+
+/// USB vendor and product IDs of the STM32 system bootloader.
+///
+/// Any STM32 board can enumerate with these IDs, so devices in the system
+/// bootloader are only flashed when picked by serial number.
+const SYSTEM_BOOTLOADER_IDS: (u16, u16) = (0x0483, 0xDF11);
+
+/// Check if a device is an Adapter.
+pub fn is_adapter(dev: &DeviceInfo) -> bool {
+    state(
+        dev.vendor_id(),
+        dev.product_id(),
+        dev.manufacturer_string(),
+        in_dfu_mode(dev),
+    )
+    .is_some()
+}
+
+/// Operating state of an Adapter, or `None` if the IDs are not an Adapter's.
+fn state(
+    vendor_id: u16,
+    product_id: u16,
+    manufacturer: Option<&str>,
+    dfu_mode: bool,
+) -> Option<DeviceState> {
+    if (vendor_id, product_id) == (VENDOR_ID, PRODUCT_ID) {
+        // the bootloader does not report the same descriptors as the
+        // application firmware.
+        if manufacturer == Some(MANUFACTURER) {
+            return Some(DeviceState::Application);
+        }
+        if dfu_mode {
+            return Some(DeviceState::Bootloader);
+        }
+    }
+
+    if (vendor_id, product_id) == SYSTEM_BOOTLOADER_IDS && dfu_mode {
+        return Some(DeviceState::SystemBootloader);
+    }
+
+    None
+}
+
+/// Operating state of an Adapter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceState {
+    /// Running the application firmware.
+    Application,
+    /// Waiting in the DFU bootloader.
+    Bootloader,
+    /// Waiting in the STM32 system bootloader.
+    SystemBootloader,
+}
+
+impl DeviceState {
+    pub fn of(dev: &DeviceInfo) -> Self {
+        state(
+            dev.vendor_id(),
+            dev.product_id(),
+            dev.manufacturer_string(),
+            in_dfu_mode(dev),
+        )
+        .unwrap_or(Self::Application)
+    }
+}
+
+impl Display for DeviceState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Application => write!(f, "application"),
+            Self::Bootloader => write!(f, "bootloader"),
+            Self::SystemBootloader => write!(f, "system bootloader"),
+        }
+    }
 }
 
-#[derive(Parser)]
-pub struct Cmd {
-    #[clap(subcommand)]
-    subcommand: Commands,
+/// Find an attached Adapter device.
+///
+/// Picks the first device found unless a serial number is given. Devices in
+/// the STM32 system bootloader are only found by serial number.
+pub fn find_device(serial: Option<&str>) -> Result<DeviceInfo> {
+    let mut devices = list_devices()?.into_iter();
+
+    match serial {
+        Some(serial) => devices
+            .find(|dev| dev.serial_number() == Some(serial))
+            .ok_or_else(|| {
+                Error::Unreachable(format!(
+                    "Adapter with serial number {} was not found.",
+                    serial
+                ))
+            }),
+        None => {
+            let (system, adapters): (Vec<_>, Vec<_>) =
+                devices.partition(|dev| {
+                    DeviceState::of(dev) == DeviceState::SystemBootloader
+                });
+
+            match (adapters.into_iter().next(), system.first()) {
+                (Some(dev), _) => Ok(dev),
+                (None, Some(dev)) => Err(Error::Validation(format!(
+                    "Found a device in the STM32 system bootloader, which may not be an Adapter. Use --serial {} to flash it anyway.",
+                    dev.serial_number().unwrap_or("<serial>")
+                ))),
+                (None, None) => Err(Error::Unreachable(
+                    "No Adapter devices found.".to_string(),
+                )),
+            }
+        }
+    }
 }
 
-impl Cmd {
-    pub async fn run(self) -> anyhow::Result<()> {
-        // so we can log to files later.
-        let output = std::io::stdout();
+/// Wait for the device to re-enumerate in run-time mode.
+///
+/// If a `bcdDevice` version is given, also waits for the device to report
+/// that version.
+pub async fn wait_for_device(
+    serial: Option<&str>,
+    version: Option<u16>,
+) -> Result<DeviceInfo> {
+    let start = Instant::now();
+    let mut last_seen = None;
+
+    while start.elapsed() < REENUMERATE_TIMEOUT {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+
+        let Ok(dev) = find_device(serial) else {
+            continue;
+        };
 
-        match self.subcommand {
-            Commands::List => list::command(output).await,
-            Commands::Update(options) => update::command(output, options).await,
+        if DeviceState::of(&dev) != DeviceState::Application {
+            continue;
         }
+
+        let reported = dev.device_version();
+        if version.is_none_or(|v| v == reported) {
+            return Ok(dev);
+        }
+
+        last_seen = Some(reported);
+    }
+
+    match (last_seen, version) {
+        (Some(reported), Some(expected)) => Err(Error::Firmware(format!(
+            "Device reported firmware version {} after update, expected {}.",
+            format_bcd_version(reported),
+            format_bcd_version(expected)
+        ))),
+        _ => Err(Error::Timeout(
+            "Device did not re-enumerate after update. Please reconnect the device and try again."
+                .to_string(),
+        )),
     }
 }
 
-#[derive(Parser)]
-pub struct UpdateOptions {
-    /// Update using firmware file.
-    #[clap(long)]
-    file: Option<PathBuf>,
-    /// Update to a specific version.
-    #[clap(long)]
-    version: Option<String>,
+/// Format a binary coded decimal `bcdDevice` as a version string.
+pub fn format_bcd_version(bcd: u16) -> String {
+    format!(
+        "v{}.{}.{}",
+        (bcd >> 12) * 10 + ((bcd >> 8) & 0xF),
+        (bcd >> 4) & 0xF,
+        bcd & 0xF
+    )
+}
+
+/// Binary coded decimal `bcdDevice` of a release version, e.g. `v1.3.0-beta.1`.
+///
+/// Pre-release and build metadata are ignored. Returns `None` for versions
+/// that can't be written in BCD, such as `v1.10.0`.
+pub fn bcd_version(version: &str) -> Option<u16> {
+    let version =
+        semver::Version::parse(version.trim_start_matches('v')).ok()?;
+
+    if version.major > 99 || version.minor > 9 || version.patch > 9 {
+        return None;
+    }
+
+    Some(
+        ((version.major / 10) << 12
+            | (version.major % 10) << 8
+            | version.minor << 4
+            | version.patch) as u16,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adapter_ids() {
+        use DeviceState::*;
+
+        let umi = Some(MANUFACTURER);
+        assert_eq!(state(0x1209, 0x2323, umi, false), Some(Application));
+        assert_eq!(state(0x1209, 0x2323, umi, true), Some(Application));
+        assert_eq!(state(0x1209, 0x2323, Some("Other"), false), None);
+        assert_eq!(state(0x1209, 0x2323, None, true), Some(Bootloader));
+        // other boards sharing the pid.codes vendor ID.
+        assert_eq!(state(0x1209, 0x0001, None, true), None);
+        assert_eq!(
+            state(0x0483, 0xDF11, Some("STMicroelectronics"), true),
+            Some(SystemBootloader)
+        );
+        assert_eq!(state(0x0483, 0xDF11, None, false), None);
+        assert_eq!(state(0x0483, 0x5740, None, true), None);
+    }
+
+    #[test]
+    fn bcd_versions() {
+        assert_eq!(format_bcd_version(0x0100), "v1.0.0");
+        assert_eq!(format_bcd_version(0x1234), "v12.3.4");
+
+        assert_eq!(bcd_version("v1.0.0"), Some(0x0100));
+        assert_eq!(bcd_version("v12.3.4"), Some(0x1234));
+        assert_eq!(bcd_version("v1.3.0-beta.1"), Some(0x0130));
+        assert_eq!(bcd_version("v1.10.0"), None);
+        assert_eq!(bcd_version("v100.0.0"), None);
+        assert_eq!(bcd_version("latest"), None);
+    }
 }
@@ -0,0 +1,65 @@
+//! Firmware releases published on GitHub.
+
+use crate::{
+    error::{Error, Result},
+    http,
+};
+use serde::Deserialize;
+
+/// Releases endpoint of the firmware repository.
+const RELEASES_URL: &str =
+    "https://api.github.com/repos/umi-eng/adapter/releases";
+
+#[derive(Debug, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    pub draft: bool,
+    pub prerelease: bool,
+    /// Release notes.
+    pub body: Option<String>,
+    pub published_at: Option<String>,
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Asset {
+    pub name: String,
+    pub size: u64,
+    pub browser_download_url: String,
+}
+
+impl Asset {
+    /// Download the asset.
+    pub async fn download(&self) -> Result<Vec<u8>> {
+        http::get_bytes(&self.browser_download_url)
+            .await
+            .map_err(|err| {
+                Error::Firmware(format!(
+                    "Could not download {}: {}.",
+                    self.name,
+                    http::describe(&err)
+                ))
+            })
+    }
+}
+
+impl Release {
+    /// Find the firmware file in the release assets.
+    pub fn firmware(&self) -> Option<&Asset> {
+        self.assets.iter().find(|a| {
+            a.name.starts_with("umi-adapter-v") && a.name.ends_with(".bin")
+        })
+    }
+}
+
+/// Fetch releases, newest first.
+pub async fn fetch() -> Result<Vec<Release>> {
+    let releases = async { http::get(RELEASES_URL).await?.json().await };
+
+    releases.await.map_err(|err| {
+        Error::Firmware(format!(
+            "Could not fetch firmware releases: {}.",
+            http::describe(&err)
+        ))
+    })
+}
@@ -0,0 +1,56 @@
+//! Firmware release channels.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{convert::Infallible, fmt::Display, str::FromStr};
+
+/// Firmware release channel.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Channel {
+    /// Latest stable release.
+    #[default]
+    Stable,
+    /// Latest release, including pre-releases.
+    Latest,
+    /// Named channel such as "beta".
+    Named(String),
+}
+
+impl FromStr for Channel {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "stable" => Self::Stable,
+            "latest" => Self::Latest,
+            name => Self::Named(name.to_string()),
+        })
+    }
+}
+
+impl Display for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Stable => write!(f, "stable"),
+            Self::Latest => write!(f, "latest"),
+            Self::Named(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl Serialize for Channel {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Channel {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(s.parse().unwrap_or_default())
+    }
+}
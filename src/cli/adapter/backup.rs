@@ -0,0 +1,43 @@
+//! Firmware backup.
+
+use super::list::{record, resolve_serial};
+use super::BackupOptions;
+use crate::{cli::inventory, write_with_header};
+use colored::Colorize;
+use umi::adapter::{dfu, find_device, format_bcd_version};
+
+pub async fn command(
+    mut output: impl std::io::Write,
+    options: BackupOptions,
+) -> anyhow::Result<()> {
+    let serial = resolve_serial(options.serial.as_deref())?;
+    let dev = find_device(serial.as_deref())?;
+    inventory::remember(record(&dev));
+
+    write_with_header(
+        &mut output,
+        "Reading firmware...".green(),
+        &format!(
+            "{} {}",
+            dev.serial_number().unwrap_or("-"),
+            format_bcd_version(dev.device_version())
+        ),
+    );
+
+    let dfu = dfu::open(&dev)?;
+    let image = dfu::upload(&dfu, None).await?;
+
+    tokio::fs::write(&options.output, &image).await?;
+
+    write_with_header(
+        &mut output,
+        "Done...".green(),
+        &format!(
+            "{} bytes written to {}",
+            image.len(),
+            options.output.display()
+        ),
+    );
+
+    Ok(())
+}
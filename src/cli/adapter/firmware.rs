@@ -0,0 +1,122 @@
+//! Available firmware releases.
+
+use crate::write_with_header;
+use clap::{Parser, Subcommand};
+use colored::Colorize;
+use umi::{adapter::release, error::Error};
+
+#[derive(Subcommand)]
+enum Commands {
+    /// List available firmware versions.
+    List,
+    /// Show details and release notes of a firmware version.
+    Show(Show),
+}
+
+#[derive(Parser)]
+struct Show {
+    /// Firmware version, e.g. "v0.2.0".
+    version: String,
+}
+
+#[derive(Parser)]
+pub struct Cmd {
+    #[clap(subcommand)]
+    subcommand: Commands,
+}
+
+impl Cmd {
+    pub async fn run(
+        self,
+        mut output: impl std::io::Write,
+    ) -> anyhow::Result<()> {
+        let releases = release::fetch().await?;
+
+        match self.subcommand {
+            Commands::List => {
+                let stable = releases
+                    .iter()
+                    .find(|r| !r.draft && !r.prerelease)
+                    .map(|r| r.tag_name.as_str());
+
+                writeln!(
+                    output,
+                    "{0: <16} {1: <12} {2: <12} Assets",
+                    "Version", "Published", "Channel"
+                )?;
+                for release in releases.iter().filter(|r| !r.draft) {
+                    let channel = if Some(release.tag_name.as_str()) == stable {
+                        "stable"
+                    } else if release.prerelease {
+                        "pre-release"
+                    } else {
+                        ""
+                    };
+                    let assets = release
+                        .assets
+                        .iter()
+                        .map(|a| a.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+
+                    writeln!(
+                        output,
+                        "{0: <16} {1: <12} {2: <12} {3}",
+                        release.tag_name,
+                        release
+                            .published_at
+                            .as_deref()
+                            .and_then(|p| p.get(..10))
+                            .unwrap_or("-"),
+                        channel,
+                        assets
+                    )?;
+                }
+
+                Ok(())
+            }
+            Commands::Show(show) => {
+                let release = releases
+                    .iter()
+                    .find(|r| !r.draft && r.tag_name == show.version)
+                    .ok_or_else(|| {
+                        Error::Firmware(format!(
+                            "Firmware version {} was not found.",
+                            show.version
+                        ))
+                    })?;
+
+                write_with_header(
+                    &mut output,
+                    "Version".green(),
+                    &release.tag_name,
+                );
+                write_with_header(
+                    &mut output,
+                    "Published".green(),
+                    release.published_at.as_deref().unwrap_or("-"),
+                );
+                write_with_header(
+                    &mut output,
+                    "Pre-release".green(),
+                    &format!("{}", release.prerelease),
+                );
+                for asset in &release.assets {
+                    write_with_header(
+                        &mut output,
+                        "Asset".green(),
+                        &format!("{} ({} bytes)", asset.name, asset.size),
+                    );
+                }
+                writeln!(output)?;
+                writeln!(
+                    output,
+                    "{}",
+                    release.body.as_deref().unwrap_or("No release notes.")
+                )?;
+
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! Device information.
+
+use super::list::{record, resolve_serial};
+use super::InfoOptions;
+use crate::{cli::inventory, write_with_header};
+use colored::Colorize;
+use dfu_core::functional_descriptor::FunctionalDescriptor;
+use nusb::{DeviceInfo, Speed};
+use umi::adapter::{
+    dfu::{DFU_CLASS, DFU_SUBCLASS},
+    find_device, format_bcd_version, DeviceState,
+};
+
+pub async fn command(
+    mut output: impl std::io::Write,
+    options: InfoOptions,
+) -> anyhow::Result<()> {
+    let serial = resolve_serial(options.serial.as_deref())?;
+    let dev = find_device(serial.as_deref())?;
+    inventory::remember(record(&dev));
+
+    write_with_header(
+        &mut output,
+        "Serial".green(),
+        dev.serial_number().unwrap_or("-"),
+    );
+
+    write_with_header(
+        &mut output,
+        "Product".green(),
+        dev.product_string().unwrap_or("-"),
+    );
+
+    write_with_header(
+        &mut output,
+        "Firmware Version".green(),
+        &format_bcd_version(dev.device_version()),
+    );
+
+    write_with_header(
+        &mut output,
+        "USB ID".green(),
+        &format!("{:04x}:{:04x}", dev.vendor_id(), dev.product_id()),
+    );
+
+    write_with_header(&mut output, "Location".green(), &location(&dev));
+
+    write_with_header(
+        &mut output,
+        "Speed".green(),
+        match dev.speed() {
+            Some(Speed::Low) => "Low (1.5 Mbit/s)",
+            Some(Speed::Full) => "Full (12 Mbit/s)",
+            Some(Speed::High) => "High (480 Mbit/s)",
+            Some(Speed::Super) => "Super (5 Gbit/s)",
+            Some(Speed::SuperPlus) => "Super+ (10 Gbit/s)",
+            _ => "Unknown",
+        },
+    );
+
+    write_with_header(
+        &mut output,
+        "State".green(),
+        &format!("{}", DeviceState::of(&dev)),
+    );
+
+    let device = match dev.open() {
+        Ok(device) => device,
+        Err(err) => {
+            write_with_header(
+                &mut output,
+                "Interfaces".green(),
+                &format!("Could not open device: {}", err),
+            );
+            return Ok(());
+        }
+    };
+
+    let config = device.active_configuration()?;
+
+    let mut interfaces = String::new();
+    for alt in config.interface_alt_settings() {
+        interfaces.push_str(&format!(
+            "#{} alt {}: class {:02x} subclass {:02x} protocol {:02x}\n",
+            alt.interface_number(),
+            alt.alternate_setting(),
+            alt.class(),
+            alt.subclass(),
+            alt.protocol(),
+        ));
+
+        if alt.class() != DFU_CLASS || alt.subclass() != DFU_SUBCLASS {
+            continue;
+        }
+
+        if let Some(Ok(dfu)) = alt
+            .descriptors()
+            .find_map(|d| FunctionalDescriptor::from_bytes(&d))
+        {
+            interfaces.push_str(&format_functional_descriptor(&dfu));
+        }
+    }
+
+    write_with_header(&mut output, "Interfaces".green(), &interfaces);
+
+    Ok(())
+}
+
+fn format_functional_descriptor(dfu: &FunctionalDescriptor) -> String {
+    format!(
+        "  DFU v{}.{}: transfer size {} bytes, detach timeout {} ms\n  \
+         download {}, upload {}, manifestation tolerant {}, will detach {}\n",
+        dfu.dfu_version.0,
+        dfu.dfu_version.1,
+        dfu.transfer_size,
+        dfu.detach_timeout,
+        dfu.can_download,
+        dfu.can_upload,
+        dfu.manifestation_tolerant,
+        dfu.will_detach,
+    )
+}
+
+/// Bus and port path of the device.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn location(dev: &DeviceInfo) -> String {
+    let port = dev
+        .sysfs_path()
+        .file_name()
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    format!(
+        "bus {} address {} port {}",
+        dev.bus_number(),
+        dev.device_address(),
+        port
+    )
+}
+
+/// Bus and port path of the device.
+#[cfg(target_os = "windows")]
+fn location(dev: &DeviceInfo) -> String {
+    format!(
+        "bus {} address {} port {}",
+        dev.bus_number(),
+        dev.device_address(),
+        dev.port_number()
+    )
+}
+
+/// Bus and port path of the device.
+#[cfg(target_os = "macos")]
+fn location(dev: &DeviceInfo) -> String {
+    format!(
+        "bus {} address {} location {:08x}",
+        dev.bus_number(),
+        dev.device_address(),
+        dev.location_id()
+    )
+}
@@ -0,0 +1,62 @@
+use crate::{
+    cli::config::Config,
+    cli::inventory::{self, Kind, Record},
+    write_with_header,
+};
+use colored::Colorize;
+use nusb::DeviceInfo;
+use umi::adapter::{format_bcd_version, list_devices, DeviceState};
+
+/// Resolve a device name from the config file to a serial number.
+pub fn resolve_serial(serial: Option<&str>) -> anyhow::Result<Option<String>> {
+    match serial {
+        Some(serial) => Ok(Some(Config::load()?.adapter.serial(serial))),
+        None => Ok(None),
+    }
+}
+
+/// Inventory record of an Adapter.
+///
+/// Devices without a serial number can't be tracked, and devices in the STM32
+/// system bootloader may not be Adapters.
+pub fn record(dev: &DeviceInfo) -> Option<Record> {
+    if DeviceState::of(dev) == DeviceState::SystemBootloader {
+        return None;
+    }
+
+    let mut record =
+        Record::new(Kind::Adapter, dev.serial_number()?.to_string());
+    record.device_type = dev.product_string().map(String::from);
+    record.firmware_version = Some(format_bcd_version(dev.device_version()));
+    Some(record)
+}
+
+pub async fn command(mut output: impl std::io::Write) -> anyhow::Result<()> {
+    let devices = list_devices()?;
+
+    if devices.is_empty() {
+        write_with_header(
+            &mut output,
+            "No Adapter devices found...".green(),
+            " ",
+        );
+        return Ok(());
+    }
+
+    inventory::remember(devices.iter().filter_map(record));
+
+    println!(
+        "{0: <10} {1: <18} {2: <10}",
+        "Serial No.", "State", "Product"
+    );
+    devices.iter().for_each(|dev| {
+        println!(
+            "{0: <10} {1: <18} {2: <10}",
+            dev.serial_number().unwrap_or("-"),
+            DeviceState::of(dev).to_string(),
+            dev.product_string().unwrap_or("-")
+        )
+    });
+
+    Ok(())
+}
@@ -0,0 +1,116 @@
+mod backup;
+mod firmware;
+mod info;
+mod list;
+pub mod update;
+mod watch;
+
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use umi::channel::Channel;
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// List devices.
+    List,
+    /// Show device details.
+    Info(InfoOptions),
+    /// Update firmware
+    Update(UpdateOptions),
+    /// Save the current firmware image to a file.
+    Backup(BackupOptions),
+    /// Watch for devices being attached and detached.
+    Watch(WatchOptions),
+    /// List available firmware versions.
+    Firmware(firmware::Cmd),
+}
+
+#[derive(Parser)]
+pub struct Cmd {
+    #[clap(subcommand)]
+    subcommand: Commands,
+}
+
+impl Cmd {
+    pub async fn run(self) -> anyhow::Result<()> {
+        let output = std::io::stdout();
+
+        match self.subcommand {
+            Commands::List => list::command(output).await,
+            Commands::Info(options) => info::command(output, options).await,
+            Commands::Update(options) => {
+                update::command(output, options, |_, _| {}).await
+            }
+            Commands::Backup(options) => backup::command(output, options).await,
+            Commands::Watch(options) => watch::command(output, options).await,
+            Commands::Firmware(command) => command.run(output).await,
+        }
+    }
+}
+
+#[derive(Parser, Default)]
+pub struct UpdateOptions {
+    /// Serial number or device name from the config file.
+    ///
+    /// Required to flash a device in the STM32 system bootloader.
+    #[clap(long)]
+    serial: Option<String>,
+    /// Update using firmware file.
+    ///
+    /// A DFU suffix is checked and removed, and its firmware version is
+    /// confirmed after the update.
+    #[clap(long)]
+    file: Option<PathBuf>,
+    /// Update to a specific version.
+    #[clap(long, conflicts_with = "channel")]
+    version: Option<String>,
+    /// Release channel: "stable", "latest" or a named channel.
+    ///
+    /// Defaults to the channel set in the config file, or "stable".
+    #[clap(long)]
+    channel: Option<Channel>,
+    /// Remember the release channel for this device.
+    #[clap(long, requires = "channel")]
+    persist: bool,
+}
+
+impl UpdateOptions {
+    /// Update a device to its release channel.
+    pub fn serial(serial: String) -> Self {
+        Self {
+            serial: Some(serial),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Parser)]
+pub struct InfoOptions {
+    /// Serial number or device name from the config file.
+    #[clap(long)]
+    serial: Option<String>,
+}
+
+#[derive(Parser)]
+pub struct WatchOptions {
+    /// Output events as JSON Lines.
+    #[clap(long)]
+    json: bool,
+    /// Command to run when a device is attached.
+    ///
+    /// The serial number, firmware version and state are passed in the
+    /// `UMI_SERIAL`, `UMI_FIRMWARE_VERSION` and `UMI_STATE` environment
+    /// variables. The command runs in the background and its output is
+    /// written to stderr.
+    #[clap(long)]
+    exec: Option<String>,
+}
+
+#[derive(Parser)]
+pub struct BackupOptions {
+    /// File to write the firmware image to.
+    output: PathBuf,
+    /// Serial number or device name from the config file.
+    #[clap(long)]
+    serial: Option<String>,
+}
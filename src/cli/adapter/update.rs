@@ -0,0 +1,191 @@
+//! Firmware update.
+
+use super::list::{record, resolve_serial};
+use super::UpdateOptions;
+use crate::{cli::config::Config, cli::inventory, write_with_header};
+use colored::Colorize;
+use dfu_core::asynchronous::DfuAsyncIo;
+use tracing::debug;
+use umi::{
+    adapter::{
+        bcd_version, dfu, find_device, format_bcd_version,
+        release::{self, Release},
+        wait_for_device, DeviceState,
+    },
+    channel::Channel,
+    error::Error,
+};
+
+/// Update an Adapter.
+///
+/// `progress` is called with the block number and total number of blocks as
+/// the firmware is loaded.
+pub async fn command(
+    mut output: impl std::io::Write,
+    options: UpdateOptions,
+    progress: impl FnMut(u32, u32) + Unpin,
+) -> anyhow::Result<()> {
+    write_with_header(&mut output, "Finding devices...".green(), " ");
+
+    let serial = resolve_serial(options.serial.as_deref())?;
+    let dev = find_device(serial.as_deref())?;
+    let serial = dev.serial_number().map(String::from);
+
+    let (firmware, version) = if let Some(file_path) = options.file {
+        write_with_header(&mut output, "Reading firmware file...".green(), " ");
+        let contents = tokio::fs::read(file_path).await?;
+        let (image, version) = dfu::split_suffix(&contents)?;
+        (image.to_vec(), version)
+    } else {
+        let selector = match options.version {
+            Some(version) => Selector::Version(version),
+            None => {
+                let mut config = Config::load()?;
+                let device = serial.clone().unwrap_or_default();
+                let channel = match options.channel {
+                    Some(channel) => {
+                        if options.persist {
+                            config
+                                .adapter
+                                .set_channel(&device, channel.clone());
+                            config.save()?;
+                        }
+                        channel
+                    }
+                    None => config.adapter.channel(&device),
+                };
+                Selector::Channel(channel)
+            }
+        };
+
+        let (file, tag) = download_firmware(&mut output, &selector).await?;
+        let (image, version) = dfu::split_suffix(&file)?;
+        (image.to_vec(), version.or(bcd_version(&tag)))
+    };
+
+    match DeviceState::of(&dev) {
+        DeviceState::Application => {}
+        DeviceState::Bootloader => write_with_header(
+            &mut output,
+            "Recovering...".yellow(),
+            "Device is in bootloader mode.",
+        ),
+        DeviceState::SystemBootloader => write_with_header(
+            &mut output,
+            "Recovering...".yellow(),
+            "Device is in the STM32 system bootloader.",
+        ),
+    }
+
+    write_with_header(&mut output, "Loading new firmware...".green(), " ");
+    debug!(len = firmware.len(), "downloading image");
+    let dfu = dfu::download(dfu::open(&dev)?, &firmware, progress).await?;
+
+    // devices that are not manifestation tolerant reset into the application
+    // after the download, which can't be read back, so for those rely on the
+    // file checksum and the reported version.
+    if dfu.functional_descriptor().manifestation_tolerant {
+        write_with_header(&mut output, "Verifying...".green(), " ");
+        dfu::verify(&dfu, &firmware).await?;
+        // the device may disconnect before acknowledging the reset.
+        let _ = dfu.usb_reset().await;
+    }
+    drop(dfu);
+
+    if version.is_none() {
+        write_with_header(
+            &mut output,
+            "Warning".yellow(),
+            "The firmware version can't be confirmed after the update.",
+        );
+    }
+
+    write_with_header(&mut output, "Waiting for device...".green(), " ");
+    let dev = wait_for_device(serial.as_deref(), version).await?;
+    inventory::remember(record(&dev));
+
+    write_with_header(
+        &mut output,
+        "Done...".green(),
+        &format_bcd_version(dev.device_version()),
+    );
+
+    Ok(())
+}
+
+/// How to select a release.
+enum Selector {
+    /// Release with an exact tag.
+    Version(String),
+    /// Newest release in a channel.
+    Channel(Channel),
+}
+
+impl Selector {
+    /// Check if a release matches.
+    ///
+    /// Channels map onto the GitHub pre-release flag. Named channels match
+    /// pre-releases tagged with the channel name, e.g. `v1.2.0-beta.1`.
+    fn matches(&self, release: &Release) -> bool {
+        if release.draft {
+            return false;
+        }
+
+        match self {
+            Self::Version(version) => &release.tag_name == version,
+            Self::Channel(Channel::Stable) => !release.prerelease,
+            Self::Channel(Channel::Latest) => true,
+            Self::Channel(Channel::Named(name)) => {
+                release.prerelease
+                    && release.tag_name.contains(&format!("-{}", name))
+            }
+        }
+    }
+}
+
+/// Download the firmware for a release, returning the image and its version.
+async fn download_firmware(
+    mut output: impl std::io::Write,
+    selector: &Selector,
+) -> anyhow::Result<(Vec<u8>, String)> {
+    write_with_header(&mut output, "Finding firmware...".green(), " ");
+
+    let releases = release::fetch().await?;
+
+    if releases.is_empty() {
+        return Err(Error::Firmware("No releases found.".to_string()).into());
+    }
+
+    // releases are listed newest first.
+    let release =
+        releases
+            .iter()
+            .find(|r| selector.matches(r))
+            .ok_or_else(|| match selector {
+                Selector::Version(version) => Error::Firmware(format!(
+                    "Firmware version {} was not found. Run `umi adapter firmware list` to see available versions.",
+                    version
+                )),
+                Selector::Channel(channel) => Error::Firmware(format!(
+                    "Could not find a release in the {} channel.",
+                    channel
+                )),
+            })?;
+
+    let Some(asset) = release.firmware() else {
+        return Err(Error::Firmware(
+            "Could not find firmware file in release.".to_string(),
+        )
+        .into());
+    };
+
+    write_with_header(
+        &mut output,
+        "Downloading firmware...".green(),
+        &release.tag_name,
+    );
+
+    let firmware = asset.download().await?;
+
+    Ok((firmware, release.tag_name.clone()))
+}
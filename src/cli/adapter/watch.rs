@@ -0,0 +1,148 @@
+//! Hotplug event watching.
+
+use super::list::record;
+use super::WatchOptions;
+use crate::{cli::inventory, write_with_header};
+use colored::Colorize;
+use futures::StreamExt;
+use nusb::{hotplug::HotplugEvent, DeviceId, DeviceInfo};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::process::Command;
+use umi::adapter::{format_bcd_version, is_adapter, list_devices, DeviceState};
+
+pub async fn command(
+    mut output: impl std::io::Write,
+    options: WatchOptions,
+) -> anyhow::Result<()> {
+    // create the watch before listing so no device is missed in between.
+    let mut watch = nusb::watch_devices()?;
+
+    let mut devices: HashMap<DeviceId, DeviceInfo> =
+        list_devices()?.into_iter().map(|d| (d.id(), d)).collect();
+
+    if !options.json {
+        write_with_header(
+            &mut output,
+            "Watching".green(),
+            &format!("{} Adapter(s) attached", devices.len()),
+        );
+    }
+
+    while let Some(event) = watch.next().await {
+        let (kind, dev) = match event {
+            HotplugEvent::Connected(dev) if is_adapter(&dev) => {
+                inventory::remember(record(&dev));
+                devices.insert(dev.id(), dev.clone());
+                (EventKind::Connected, dev)
+            }
+            HotplugEvent::Disconnected(id) => match devices.remove(&id) {
+                Some(dev) => (EventKind::Disconnected, dev),
+                None => continue,
+            },
+            _ => continue,
+        };
+
+        let event = Event {
+            event: kind,
+            serial: dev.serial_number().map(String::from),
+            firmware_version: format_bcd_version(dev.device_version()),
+            state: DeviceState::of(&dev).to_string(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|t| t.as_secs())
+                .unwrap_or_default(),
+        };
+
+        if options.json {
+            writeln!(output, "{}", serde_json::to_string(&event)?)?;
+        } else {
+            let header = match event.event {
+                EventKind::Connected => "Connected".green(),
+                EventKind::Disconnected => "Disconnected".yellow(),
+            };
+            write_with_header(
+                &mut output,
+                header,
+                &format!(
+                    "{} {} ({})",
+                    event.serial.as_deref().unwrap_or("-"),
+                    event.firmware_version,
+                    event.state
+                ),
+            );
+        }
+        output.flush()?;
+
+        if let (EventKind::Connected, Some(exec)) = (event.event, &options.exec)
+        {
+            run_hook(exec, &event);
+        }
+    }
+
+    Ok(())
+}
+
+/// Start the attach hook through the system shell.
+///
+/// Details of the device are passed in environment variables. The hook runs
+/// in the background with its output sent to stderr so it cannot interleave
+/// with events, and failures are reported without stopping the watch.
+fn run_hook(exec: &str, event: &Event) {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(exec);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(exec);
+        command
+    };
+
+    let child = command
+        .env("UMI_SERIAL", event.serial.as_deref().unwrap_or_default())
+        .env("UMI_FIRMWARE_VERSION", &event.firmware_version)
+        .env("UMI_STATE", &event.state)
+        .stdin(Stdio::null())
+        .stdout(std::io::stderr())
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) => {
+            hook_failed(&err.to_string());
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        match child.wait().await {
+            Ok(status) if !status.success() => hook_failed(&status.to_string()),
+            Ok(_) => {}
+            Err(err) => hook_failed(&err.to_string()),
+        }
+    });
+}
+
+fn hook_failed(reason: &str) {
+    write_with_header(std::io::stderr(), "Hook failed".red(), reason);
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum EventKind {
+    Connected,
+    Disconnected,
+}
+
+#[derive(Debug, Serialize)]
+struct Event {
+    event: EventKind,
+    serial: Option<String>,
+    firmware_version: String,
+    state: String,
+    /// Seconds since the Unix epoch.
+    timestamp: u64,
+}
@@ -0,0 +1,266 @@
+//! User configuration file.
+
+use crate::cli::inventory::{Inventory, Kind};
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, net::IpAddr, path::PathBuf};
+use umi::channel::Channel;
+
+/// User configuration.
+///
+/// # Example
+///
+/// ```toml
+/// [gateway]
+/// channel = "stable"
+///
+/// [gateway.devices."192.168.1.10"]
+/// channel = "latest"
+///
+/// [gateway.devices.line3-can]
+/// address = "192.168.1.20"
+/// port = 5020
+/// unit_id = 2
+///
+/// [gateway.devices.line4-can]
+/// serial = "2412-00AB"
+///
+/// [adapter]
+/// channel = "latest"
+///
+/// [adapter.devices.bench-1]
+/// serial = "A1B2C3D4"
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Config {
+    /// Gateway settings.
+    #[serde(default)]
+    pub gateway: Fleet,
+    /// Adapter settings.
+    #[serde(default)]
+    pub adapter: Fleet,
+}
+
+/// Settings for a fleet of devices of one type.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Fleet {
+    /// Default firmware channel.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<Channel>,
+    /// Per-device settings.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub devices: BTreeMap<String, Device>,
+}
+
+/// Settings for a single device.
+///
+/// Devices are keyed by a name, or by the address or serial number itself
+/// when only defaults are set.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Device {
+    /// Gateway IP address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<IpAddr>,
+    /// Serial number. Gateways without an address are reached at the last
+    /// address recorded for the serial in the inventory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+    /// Modbus TCP port.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// Modbus unit ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit_id: Option<u8>,
+    /// Firmware channel.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<Channel>,
+}
+
+impl Config {
+    /// Location of the configuration file.
+    pub fn path() -> anyhow::Result<PathBuf> {
+        let dir = dirs::config_dir().ok_or_else(|| {
+            Error::msg("Could not find the user configuration directory.")
+        })?;
+
+        Ok(dir.join("umi").join("config.toml"))
+    }
+
+    /// Load the configuration, or the default if there is no file.
+    pub fn load() -> anyhow::Result<Self> {
+        let path = Self::path()?;
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents).map_err(|err| {
+                umi::error::Error::Validation(format!(
+                    "Invalid config {}: {}",
+                    path.display(),
+                    err
+                ))
+                .into()
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                Ok(Self::default())
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Save the configuration.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = Self::path()?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+}
+
+impl Fleet {
+    /// Find the settings for a device by name, address or serial number.
+    pub fn device(&self, device: &str) -> Option<(&str, &Device)> {
+        if let Some((name, settings)) = self.devices.get_key_value(device) {
+            return Some((name, settings));
+        }
+
+        self.devices
+            .iter()
+            .find(|(_, d)| {
+                d.serial.as_deref() == Some(device)
+                    || d.address.map(|a| a.to_string()).as_deref()
+                        == Some(device)
+            })
+            .map(|(name, settings)| (name.as_str(), settings))
+    }
+
+    /// Resolve a device name or serial number to a gateway IP address.
+    pub fn address(&self, device: &str) -> umi::error::Result<IpAddr> {
+        self.address_from(device, |serial| {
+            Inventory::load().ok()?.address(Kind::Gateway, serial)
+        })
+    }
+
+    /// Resolve a gateway IP address, looking up serial numbers with
+    /// `last_known`.
+    fn address_from(
+        &self,
+        device: &str,
+        last_known: impl FnOnce(&str) -> Option<IpAddr>,
+    ) -> umi::error::Result<IpAddr> {
+        let settings = self.device(device).map(|(_, d)| d);
+
+        if let Some(address) = settings.and_then(|d| d.address) {
+            return Ok(address);
+        }
+        if let Ok(address) = device.parse() {
+            return Ok(address);
+        }
+
+        let serial =
+            settings.and_then(|d| d.serial.as_deref()).unwrap_or(device);
+        last_known(serial).ok_or_else(|| {
+            umi::error::Error::Validation(format!(
+                "{} is not an IP address, a configured device name or the serial number of a gateway in the inventory.",
+                device
+            ))
+        })
+    }
+
+    /// Resolve a device name to an Adapter serial number.
+    pub fn serial(&self, device: &str) -> String {
+        self.device(device)
+            .and_then(|(_, d)| d.serial.clone())
+            .unwrap_or_else(|| device.to_string())
+    }
+
+    /// Firmware channel for a device, falling back to the fleet default.
+    pub fn channel(&self, device: &str) -> Channel {
+        self.device(device)
+            .and_then(|(_, d)| d.channel.clone())
+            .or_else(|| self.channel.clone())
+            .unwrap_or_default()
+    }
+
+    /// Remember the firmware channel for a device.
+    pub fn set_channel(&mut self, device: &str, channel: Channel) {
+        let name = self
+            .device(device)
+            .map(|(name, _)| name.to_string())
+            .unwrap_or_else(|| device.to_string());
+
+        self.devices.entry(name).or_default().channel = Some(channel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_fallback() {
+        let config: Config = toml::from_str(
+            r#"
+            [gateway]
+            channel = "latest"
+
+            [gateway.devices."10.0.0.2"]
+            channel = "beta"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.gateway.channel("10.0.0.2"),
+            Channel::Named("beta".to_string())
+        );
+        assert_eq!(config.gateway.channel("10.0.0.3"), Channel::Latest);
+        assert_eq!(config.adapter.channel("ABC123"), Channel::Stable);
+    }
+
+    #[test]
+    fn device_names() {
+        let config: Config = toml::from_str(
+            r#"
+            [gateway.devices.line3-can]
+            address = "10.0.0.5"
+            channel = "latest"
+            "#,
+        )
+        .unwrap();
+
+        let address: IpAddr = "10.0.0.5".parse().unwrap();
+        assert_eq!(config.gateway.address("line3-can").unwrap(), address);
+        assert_eq!(config.gateway.address("10.0.0.5").unwrap(), address);
+        assert_eq!(config.gateway.channel("10.0.0.5"), Channel::Latest);
+    }
+
+    #[test]
+    fn gateway_serials() {
+        let config: Config = toml::from_str(
+            r#"
+            [gateway.devices.line4-can]
+            serial = "2412-00AB"
+            "#,
+        )
+        .unwrap();
+
+        let address: IpAddr = "10.0.0.6".parse().unwrap();
+        let inventory =
+            |serial: &str| (serial == "2412-00AB").then_some(address);
+        let fleet = &config.gateway;
+        assert_eq!(
+            fleet.address_from("line4-can", inventory).unwrap(),
+            address
+        );
+        assert_eq!(
+            fleet.address_from("2412-00AB", inventory).unwrap(),
+            address
+        );
+        assert!(fleet.address_from("2412-00AC", inventory).is_err());
+        assert!(fleet.address_from("line5-can", inventory).is_err());
+    }
+}
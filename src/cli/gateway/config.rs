@@ -1,7 +1,11 @@
-use super::client::{Client, DeviceIdentifier};
+use super::Target;
 use clap::{error, Parser, Subcommand};
 use colored::Colorize;
 use std::net::Ipv4Addr;
+use umi::{
+    error::Error,
+    gateway::client::{Client, DeviceIdentifier},
+};
 
 use crate::write_with_header;
 
@@ -45,76 +49,53 @@ pub struct Cmd {
 impl Cmd {
     pub async fn run(
         self,
-        mut output: impl std::io::Write,
-        ip: std::net::IpAddr,
+        output: impl std::io::Write,
+        target: &Target,
     ) -> anyhow::Result<()> {
-        let mut client = Client::connect(ip).await?;
+        let mut client = target.connect().await?;
+
+        self.apply(output, &mut client).await
+    }
 
+    /// Read or write configuration over an open connection.
+    pub async fn apply(
+        self,
+        mut output: impl std::io::Write,
+        client: &mut Client,
+    ) -> anyhow::Result<()> {
         match self.subcommand {
             Commands::Dhcp(dhcp) => {
                 if let Some(enable) = dhcp.enable {
-                    client.set_dhcp(enable).await??;
+                    client.set_dhcp(enable).await?;
                     writeln!(output, "Done")?;
                 } else {
-                    writeln!(output, "{}", client.dhcp().await??)?;
+                    writeln!(output, "{}", client.dhcp().await?)?;
                 }
                 Ok(())
             }
             Commands::Ipv4(ipv4) => {
                 if let Some(ip) = ipv4.ip {
-                    client.set_ipv4_address(ip).await??;
+                    client.set_ipv4_address(ip).await?;
                     writeln!(output, "Done")?;
                 } else {
-                    writeln!(output, "{}", client.ipv4_address().await??)?;
+                    writeln!(output, "{}", client.ipv4_address().await?)?;
                 }
 
                 Ok(())
             }
             Commands::CanBitrate(can_bitrate) => {
-                match client.device_identifier().await?? {
-                    DeviceIdentifier::CanFd => {}
-                    _ => {
-                        return Err(anyhow::Error::msg(
-                            "Device does not have a CAN interface.",
-                        ));
-                    }
-                }
+                require_can(client).await?;
 
                 if let Some(nominal) = can_bitrate.nominal {
                     // use same as nominal if not specified
                     let data = can_bitrate.data.unwrap_or(nominal);
 
-                    if nominal < 10_000 {
-                        return Err(anyhow::Error::msg(
-                            "Nominal bitrate too low.",
-                        ));
-                    }
-
-                    if nominal > 5_000_000 {
-                        return Err(anyhow::Error::msg(
-                            "Nominal bitrate too high.",
-                        ));
-                    }
-
-                    if data < 10_000 {
-                        return Err(anyhow::Error::msg(
-                            "Data bitrate too low.",
-                        ));
-                    }
-
-                    if data > 5_000_000 {
-                        return Err(anyhow::Error::msg(
-                            "Data bitrate too high.",
-                        ));
-                    }
-
-                    client.set_canbus_bitrate_nominal(data).await??;
-                    client.set_canbus_bitrate_data(data).await??;
+                    set_bitrate(client, nominal, data).await?;
 
                     writeln!(output, "Done")?;
                 } else {
-                    let nominal = client.canbus_bitrate_nominal().await??;
-                    let data = client.canbus_bitrate_data().await??;
+                    let nominal = client.canbus_bitrate_nominal().await?;
+                    let data = client.canbus_bitrate_data().await?;
 
                     write_with_header(
                         &mut output,
@@ -135,9 +116,47 @@ impl Cmd {
     }
 }
 
+/// Fail unless the gateway has a CAN interface.
+pub async fn require_can(client: &mut Client) -> umi::error::Result<()> {
+    match client.device_identifier().await? {
+        DeviceIdentifier::CanFd => Ok(()),
+        _ => Err(Error::Unsupported(
+            "Device does not have a CAN interface.".to_string(),
+        )),
+    }
+}
+
+/// Set the CAN bus bitrates after checking they are in range.
+pub async fn set_bitrate(
+    client: &mut Client,
+    nominal: u32,
+    data: u32,
+) -> umi::error::Result<()> {
+    for (name, rate) in [("Nominal", nominal), ("Data", data)] {
+        if rate < 10_000 {
+            return Err(Error::Validation(format!(
+                "{} bitrate too low.",
+                name
+            )));
+        }
+
+        if rate > 5_000_000 {
+            return Err(Error::Validation(format!(
+                "{} bitrate too high.",
+                name
+            )));
+        }
+    }
+
+    client.set_canbus_bitrate_nominal(nominal).await?;
+    client.set_canbus_bitrate_data(data).await?;
+
+    Ok(())
+}
+
 /// A more general parser for boolean values such as "enable", "disable", "on"
 /// and "off" as well as "true" and "false".
-fn parse_enable(arg: &str) -> Result<bool, error::Error> {
+pub fn parse_enable(arg: &str) -> Result<bool, error::Error> {
     match arg {
         "enable" => Ok(true),
         "true" => Ok(true),
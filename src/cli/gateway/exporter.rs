@@ -0,0 +1,270 @@
+//! Prometheus metrics exporter.
+//!
+//! Gateways are polled when metrics are scraped, reusing one connection per
+//! gateway between scrapes.
+
+use super::{status::poll, ConnectionOptions, Target};
+use crate::{cli::config::Config, write_with_header};
+use axum::{
+    extract::State, http::header::CONTENT_TYPE, response::IntoResponse,
+    routing::get, Router,
+};
+use clap::Parser;
+use colored::Colorize;
+use futures::future::join_all;
+use std::{
+    fmt::Write,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{net::TcpListener, sync::Mutex};
+use tracing::debug;
+use umi::{
+    error::Error,
+    gateway::{client::Client, status::Status},
+};
+
+/// Content type of the Prometheus text format.
+const TEXT_FORMAT: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+#[derive(Parser)]
+pub struct ExporterOptions {
+    /// Address to serve metrics on.
+    #[clap(long, default_value = "0.0.0.0:9187")]
+    listen: SocketAddr,
+    /// Comma separated gateways to poll, by IP address, device name or
+    /// serial port.
+    ///
+    /// Defaults to every gateway in the config file.
+    #[clap(long, value_name = "DEVICE", value_delimiter = ',')]
+    targets: Vec<String>,
+}
+
+pub async fn command(
+    mut output: impl std::io::Write,
+    options: ExporterOptions,
+    connection: &ConnectionOptions,
+) -> anyhow::Result<()> {
+    let fleet = Config::load()?.gateway;
+
+    let names = match options.targets.is_empty() {
+        true => fleet.devices.keys().cloned().collect(),
+        false => options.targets,
+    };
+
+    if names.is_empty() {
+        return Err(Error::Validation(
+            "No gateways to poll. Use --targets or add devices to the config file."
+                .to_string(),
+        )
+        .into());
+    }
+
+    let gateways = names
+        .iter()
+        .map(|name| {
+            Ok(Gateway {
+                target: Target::resolve(name, &fleet, connection)?,
+                client: Mutex::new(None),
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let listener = TcpListener::bind(options.listen).await.map_err(|err| {
+        Error::Validation(format!(
+            "Could not listen on {}: {}.",
+            options.listen, err
+        ))
+    })?;
+
+    write_with_header(
+        &mut output,
+        "Serving".green(),
+        &format!(
+            "metrics for {} gateways on http://{}/metrics",
+            gateways.len(),
+            options.listen
+        ),
+    );
+
+    let app = Router::new()
+        .route("/metrics", get(metrics))
+        .with_state(Arc::new(gateways));
+
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+/// Gateway polled by the exporter.
+struct Gateway {
+    target: Target,
+    /// Connection kept open between scrapes.
+    client: Mutex<Option<Client>>,
+}
+
+impl Gateway {
+    async fn sample(&self) -> Sample {
+        let mut client = self.client.lock().await;
+
+        let start = Instant::now();
+        let result = poll(&self.target, &mut client).await;
+        let latency = start.elapsed();
+
+        if let Err(err) = &result {
+            debug!(gateway = self.target.name, %err, "poll failed");
+        }
+
+        Sample {
+            gateway: self.target.name.clone(),
+            status: result.ok().map(|status| (status, latency)),
+        }
+    }
+}
+
+async fn metrics(
+    State(gateways): State<Arc<Vec<Gateway>>>,
+) -> impl IntoResponse {
+    let samples = join_all(gateways.iter().map(Gateway::sample)).await;
+    ([(CONTENT_TYPE, TEXT_FORMAT)], render(&samples))
+}
+
+/// Result of polling one gateway.
+struct Sample {
+    gateway: String,
+    /// Status and the time taken to read it, none when unreachable.
+    status: Option<(Status, Duration)>,
+}
+
+/// Render samples in the Prometheus text format.
+fn render(samples: &[Sample]) -> String {
+    let mut out = String::new();
+
+    let mut family =
+        |name: &str,
+         help: &str,
+         value: &dyn Fn(&Sample) -> Option<(String, String)>| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} gauge", name);
+            for sample in samples {
+                if let Some((labels, value)) = value(sample) {
+                    let _ = writeln!(
+                        out,
+                        "{}{{gateway=\"{}\"{}}} {}",
+                        name,
+                        escape(&sample.gateway),
+                        labels,
+                        value
+                    );
+                }
+            }
+        };
+
+    family("umi_gateway_up", "Whether the gateway responded.", &|s| {
+        Some((String::new(), (s.status.is_some() as u8).to_string()))
+    });
+
+    family(
+        "umi_gateway_modbus_latency_seconds",
+        "Time taken to read the gateway status over Modbus.",
+        &|s| {
+            let (_, latency) = s.status.as_ref()?;
+            Some((String::new(), latency.as_secs_f64().to_string()))
+        },
+    );
+
+    family("umi_gateway_info", "Gateway identity and versions.", &|s| {
+        let (status, _) = s.status.as_ref()?;
+        let labels = [
+            ("device_type", status.device.code()),
+            ("serial", status.serial.to_string()),
+            ("hardware_version", status.hardware_version.to_string()),
+            ("firmware_version", status.firmware_version.to_string()),
+        ]
+        .iter()
+        .map(|(name, value)| format!(",{}=\"{}\"", name, escape(value)))
+        .collect();
+        Some((labels, "1".to_string()))
+    });
+
+    family(
+        "umi_gateway_dhcp_enabled",
+        "Whether DHCP is enabled.",
+        &|s| {
+            let (status, _) = s.status.as_ref()?;
+            Some((String::new(), (status.dhcp as u8).to_string()))
+        },
+    );
+
+    family(
+        "umi_gateway_can_receive_errors",
+        "CAN receive error counter.",
+        &|s| {
+            let can = s.status.as_ref()?.0.can.as_ref()?;
+            Some((String::new(), can.receive_errors.to_string()))
+        },
+    );
+
+    family(
+        "umi_gateway_can_transmit_errors",
+        "CAN transmit error counter.",
+        &|s| {
+            let can = s.status.as_ref()?.0.can.as_ref()?;
+            Some((String::new(), can.transmit_errors.to_string()))
+        },
+    );
+
+    out
+}
+
+/// Escape a label value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::gateway::status;
+    use umi::gateway::client::DeviceIdentifier;
+
+    #[test]
+    fn render_samples() {
+        let status = status::sample(DeviceIdentifier::CanFd);
+
+        let text = render(&[
+            Sample {
+                gateway: "line3-can".to_string(),
+                status: Some((status, Duration::from_millis(12))),
+            },
+            Sample {
+                gateway: "10.0.0.2".to_string(),
+                status: None,
+            },
+        ]);
+
+        assert!(text.contains("# TYPE umi_gateway_up gauge\n"));
+        assert!(text.contains("umi_gateway_up{gateway=\"line3-can\"} 1\n"));
+        assert!(text.contains("umi_gateway_up{gateway=\"10.0.0.2\"} 0\n"));
+        assert!(text.contains(
+            "umi_gateway_modbus_latency_seconds{gateway=\"line3-can\"} 0.012\n"
+        ));
+        assert!(text.contains(
+            "umi_gateway_info{gateway=\"line3-can\",device_type=\"FD\",serial=\"2412-00AB\",hardware_version=\"v1.0.0\",firmware_version=\"v0.2.0\"} 1\n"
+        ));
+        assert!(text.contains(
+            "umi_gateway_can_receive_errors{gateway=\"line3-can\"} 3\n"
+        ));
+        // unreachable gateways only report reachability.
+        assert_eq!(text.matches("gateway=\"10.0.0.2\"").count(), 1);
+    }
+
+    #[test]
+    fn escape_labels() {
+        assert_eq!(escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
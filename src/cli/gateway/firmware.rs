@@ -0,0 +1,100 @@
+//! Available firmware versions.
+
+use crate::write_with_header;
+use clap::{Parser, Subcommand};
+use colored::Colorize;
+use umi::{error::Error, gateway::manifest::Manifest};
+
+#[derive(Subcommand)]
+enum Commands {
+    /// List available firmware versions.
+    List,
+    /// Show details and release notes of a firmware version.
+    Show(Show),
+}
+
+#[derive(Parser)]
+struct Show {
+    /// Firmware version, e.g. "v0.2.0".
+    version: String,
+}
+
+#[derive(Parser)]
+pub struct Cmd {
+    #[clap(subcommand)]
+    subcommand: Commands,
+}
+
+impl Cmd {
+    pub async fn run(
+        self,
+        mut output: impl std::io::Write,
+    ) -> anyhow::Result<()> {
+        let manifest = Manifest::fetch().await?;
+
+        match self.subcommand {
+            Commands::List => {
+                writeln!(
+                    output,
+                    "{0: <12} {1: <12} Channels",
+                    "Version", "Requires"
+                )?;
+                for (version, binary) in manifest.sorted_binaries() {
+                    writeln!(
+                        output,
+                        "{0: <12} {1: <12} {2}",
+                        version,
+                        binary.min,
+                        manifest.channels_of(version).join(", ")
+                    )?;
+                }
+
+                Ok(())
+            }
+            Commands::Show(show) => {
+                let binary =
+                    manifest.binaries.get(&show.version).ok_or_else(|| {
+                        Error::Firmware(format!(
+                            "Firmware version {} was not found.",
+                            show.version
+                        ))
+                    })?;
+
+                write_with_header(
+                    &mut output,
+                    "Version".green(),
+                    &show.version,
+                );
+                write_with_header(
+                    &mut output,
+                    "Channels".green(),
+                    &manifest.channels_of(&show.version).join(", "),
+                );
+                write_with_header(&mut output, "Requires".green(), &binary.min);
+                if !binary.devices.is_empty() {
+                    write_with_header(
+                        &mut output,
+                        "Devices".green(),
+                        &binary.devices.join(", "),
+                    );
+                }
+                if !binary.hardware.is_empty() {
+                    write_with_header(
+                        &mut output,
+                        "Hardware".green(),
+                        &binary.hardware.join(", "),
+                    );
+                }
+                write_with_header(&mut output, "File".green(), &binary.file);
+                writeln!(output)?;
+                writeln!(
+                    output,
+                    "{}",
+                    binary.notes.as_deref().unwrap_or("No release notes.")
+                )?;
+
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,230 @@
+pub mod config;
+mod exporter;
+mod firmware;
+pub mod reset;
+pub mod restart;
+mod shell;
+pub mod status;
+pub mod update;
+mod watch;
+
+use crate::{
+    cli::config::{Config, Fleet},
+    cli::inventory::{self, Kind, Record},
+};
+use clap::{Parser, Subcommand};
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    time::Duration,
+};
+use umi::{
+    channel::Channel,
+    error::Error,
+    gateway::{
+        client::{self, Client, Serial, Transport},
+        status::Status,
+    },
+};
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Show status
+    Status(StatusOptions),
+    /// Update firmware
+    Update(UpdateOptions),
+    /// Reset all configuration
+    Reset(reset::ResetOptions),
+    /// Restart
+    Restart(restart::RestartOptions),
+    /// Read and write configuration
+    Config(config::Cmd),
+    /// List available firmware versions
+    Firmware(firmware::Cmd),
+    /// Interactive session over a single connection
+    Shell,
+    /// Serve Prometheus metrics for a fleet of gateways
+    Exporter(exporter::ExporterOptions),
+}
+
+#[derive(Parser)]
+pub struct Cmd {
+    #[clap(subcommand)]
+    subcommand: Commands,
+    /// IP address, device name from the config file, serial number of a
+    /// gateway in the inventory or serial port, e.g.
+    /// "serial:/dev/ttyUSB0@115200".
+    #[arg(value_name = "DEVICE")]
+    device: Option<String>,
+    #[clap(flatten)]
+    connection: ConnectionOptions,
+}
+
+#[derive(Parser)]
+pub struct ConnectionOptions {
+    /// Modbus TCP port. Defaults to the config file, or 502.
+    #[clap(long, global = true)]
+    port: Option<u16>,
+    /// Modbus unit ID. Defaults to the config file, or 1.
+    #[clap(long, global = true)]
+    unit_id: Option<u8>,
+    /// Timeout for connecting and for each request, e.g. "500ms" or "3s".
+    #[clap(long, global = true, default_value = "3s", value_parser = humantime::parse_duration)]
+    timeout: Duration,
+    /// Number of times to retry a request after a connection error.
+    #[clap(long, global = true, default_value_t = 2)]
+    retries: u32,
+}
+
+impl Cmd {
+    pub async fn run(self) -> anyhow::Result<()> {
+        let output = std::io::stdout();
+
+        let target = || {
+            let device = self.device.as_deref().ok_or_else(|| {
+                Error::Validation(
+                    "An IP address or device name is required for this command."
+                        .to_string(),
+                )
+            })?;
+            Target::resolve(device, &Config::load()?.gateway, &self.connection)
+        };
+
+        match self.subcommand {
+            Commands::Status(options) => match options.watch {
+                Some(interval) => {
+                    watch::command(output, &target()?, interval).await
+                }
+                None => status::command(output, &target()?).await,
+            },
+            Commands::Update(options) => {
+                update::command(output, options, &target()?).await
+            }
+            Commands::Reset(options) => {
+                reset::command(output, options, &target()?).await
+            }
+            Commands::Restart(options) => {
+                restart::command(output, options, &target()?).await
+            }
+            Commands::Config(command) => command.run(output, &target()?).await,
+            Commands::Shell => shell::command(output, &target()?).await,
+            Commands::Firmware(command) => command.run(output).await,
+            Commands::Exporter(options) => {
+                exporter::command(output, options, &self.connection).await
+            }
+        }
+    }
+}
+
+#[derive(Parser)]
+pub struct StatusOptions {
+    /// Keep polling the status, e.g. every "500ms" or "5s". Defaults to 1s.
+    ///
+    /// Redraws in place on a terminal, otherwise writes JSON Lines.
+    #[clap(long, value_name = "INTERVAL", num_args = 0..=1, default_missing_value = "1s", value_parser = humantime::parse_duration)]
+    watch: Option<Duration>,
+}
+
+#[derive(Parser)]
+pub struct UpdateOptions {
+    /// Update using firmware file.
+    #[clap(long)]
+    file: Option<PathBuf>,
+    /// Update to a specific version.
+    #[clap(long, conflicts_with = "channel")]
+    version: Option<String>,
+    /// Release channel: "stable", "latest" or a named channel.
+    ///
+    /// Defaults to the channel set in the config file, or "stable".
+    #[clap(long)]
+    channel: Option<Channel>,
+    /// Remember the release channel for this device.
+    #[clap(long, requires = "channel")]
+    persist: bool,
+    /// Skip hardware compatibility checks.
+    ///
+    /// Needed for firmware files without a UF2 family ID.
+    #[clap(long)]
+    force: bool,
+}
+
+/// Gateway resolved from an IP address or device name.
+#[derive(Debug, Clone)]
+pub struct Target {
+    /// Name used to look up device settings in the config file.
+    pub name: String,
+    /// Modbus TCP address or serial port.
+    pub transport: Transport,
+    /// Modbus unit ID.
+    pub unit_id: u8,
+    /// Timeout and retry options.
+    pub options: client::Options,
+}
+
+impl Target {
+    /// Resolve a device using the configured device settings.
+    ///
+    /// Connection options given on the command line take precedence over the
+    /// config file.
+    pub fn resolve(
+        device: &str,
+        fleet: &Fleet,
+        connection: &ConnectionOptions,
+    ) -> anyhow::Result<Self> {
+        let settings = fleet.device(device).map(|(_, d)| d);
+
+        let transport = match device.strip_prefix("serial:") {
+            Some(port) => Transport::serial(port)?,
+            None => {
+                let port = connection
+                    .port
+                    .or(settings.and_then(|d| d.port))
+                    .unwrap_or(502);
+                Transport::Tcp(SocketAddr::new(fleet.address(device)?, port))
+            }
+        };
+
+        Ok(Self {
+            name: device.to_string(),
+            transport,
+            unit_id: connection
+                .unit_id
+                .or(settings.and_then(|d| d.unit_id))
+                .unwrap_or(1),
+            options: client::Options {
+                timeout: connection.timeout,
+                retries: connection.retries,
+            },
+        })
+    }
+
+    /// Inventory record of the gateway with a serial number.
+    pub fn record(&self, serial: &Serial) -> Record {
+        let mut record = Record::new(Kind::Gateway, serial.to_string());
+        record.address = self.ip().map(|ip| ip.to_string());
+        record
+    }
+
+    /// Record a status read from the gateway in the local inventory.
+    pub fn remember(&self, status: &Status) {
+        let mut record = self.record(&status.serial);
+        record.device_type = Some(status.device.code());
+        record.hardware_version = Some(status.hardware_version.to_string());
+        record.firmware_version = Some(status.firmware_version.to_string());
+        inventory::remember([record]);
+    }
+
+    /// IP address of a gateway connected over the network.
+    pub fn ip(&self) -> Option<IpAddr> {
+        match self.transport {
+            Transport::Tcp(addr) => Some(addr.ip()),
+            Transport::Rtu { .. } => None,
+        }
+    }
+
+    /// Open a Modbus connection to the gateway.
+    pub async fn connect(&self) -> umi::error::Result<Client> {
+        Client::connect(self.transport.clone(), self.unit_id, self.options)
+            .await
+    }
+}
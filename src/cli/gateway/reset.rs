@@ -0,0 +1,272 @@
+use super::{restart, Target};
+use crate::write_with_header;
+use anyhow::Context;
+use clap::Parser;
+use colored::Colorize;
+use std::{
+    io::IsTerminal,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    time::SystemTime,
+};
+use umi::{
+    error::Error,
+    gateway::{client::Transport, status::Status},
+};
+
+/// Factory default of the DHCP setting.
+const FACTORY_DHCP: bool = true;
+
+#[derive(Parser)]
+pub struct ResetOptions {
+    /// Reset without asking for confirmation.
+    #[clap(long, short)]
+    yes: bool,
+    /// Address the gateway comes back on after the reset, e.g. the one
+    /// assigned by DHCP. Defaults to its current address, and is required if
+    /// the gateway has a static address as it uses DHCP after the reset.
+    #[clap(long, value_name = "IP")]
+    address: Option<IpAddr>,
+    /// Do not wait for the gateway to come back.
+    #[clap(long, conflicts_with = "address")]
+    no_wait: bool,
+    /// Serial number confirmed by the caller instead of asking. No other
+    /// gateway is reset.
+    #[clap(skip)]
+    serial: Option<String>,
+}
+
+impl ResetOptions {
+    /// Options for callers that confirmed the reset of the gateway with a
+    /// serial number.
+    pub fn confirmed(
+        serial: String,
+        address: Option<IpAddr>,
+        no_wait: bool,
+    ) -> Self {
+        Self {
+            yes: false,
+            address,
+            no_wait,
+            serial: Some(serial),
+        }
+    }
+}
+
+pub async fn command(
+    mut output: impl std::io::Write,
+    options: ResetOptions,
+    target: &Target,
+) -> anyhow::Result<()> {
+    let mut client = target.connect().await?;
+
+    let before = Status::read(&mut client).await?;
+    target.remember(&before);
+    write_with_header(
+        &mut output,
+        "Device".green(),
+        &format!("{} ({})", before.device.name(), before.device.code()),
+    );
+    write_with_header(
+        &mut output,
+        "Serial".green(),
+        &before.serial.to_string(),
+    );
+
+    // a static address is lost, so there is nowhere to wait.
+    let address_changes = FACTORY_DHCP && !before.dhcp;
+    if address_changes
+        && target.ip().is_some()
+        && options.address.is_none()
+        && !options.no_wait
+    {
+        return Err(Error::Validation(format!(
+            "Gateway has the static address {} and gets a new one from DHCP after the reset. Give the new address with --address, or use --no-wait.",
+            before.ipv4_address
+        ))
+        .into());
+    }
+
+    match &options.serial {
+        Some(serial) if *serial != before.serial.to_string() => {
+            return Err(Error::Validation(format!(
+                "Gateway serial number is {}, not the confirmed {}.",
+                before.serial, serial
+            ))
+            .into())
+        }
+        Some(_) => {}
+        None if options.yes => {}
+        None => {
+            if !confirm(&mut output, &before)? {
+                write_with_header(&mut output, "Cancelled".yellow(), " ");
+                return Ok(());
+            }
+        }
+    }
+
+    let backup = backup(&before)?;
+    write_with_header(
+        &mut output,
+        "Backup".green(),
+        &backup.display().to_string(),
+    );
+
+    write_with_header(&mut output, "Resetting".green(), " ");
+    // the gateway may not respond before it goes down.
+    let _ = client.reset().await;
+    drop(client);
+
+    if options.no_wait {
+        write_with_header(&mut output, "Done".green(), " ");
+        return Ok(());
+    }
+
+    restart::wait_offline(target, restart::READY_TIMEOUT).await?;
+
+    let mut target = target.clone();
+    if let Some(ip) = options.address {
+        match &mut target.transport {
+            Transport::Tcp(addr) => *addr = SocketAddr::new(ip, addr.port()),
+            Transport::Rtu { .. } => {
+                return Err(Error::Validation(
+                    "An address can only be given for network connections."
+                        .to_string(),
+                )
+                .into())
+            }
+        }
+    }
+
+    write_with_header(
+        &mut output,
+        "Waiting".green(),
+        &target.transport.to_string(),
+    );
+    let mut client =
+        restart::reconnect(&target, restart::READY_TIMEOUT).await?;
+
+    let after = Status::read(&mut client).await?;
+    check_factory(&before, &after)?;
+    target.remember(&after);
+    report(&mut output, &after);
+    write_with_header(&mut output, "Done".green(), " ");
+
+    Ok(())
+}
+
+/// Ask on the terminal before resetting.
+fn confirm(
+    mut output: impl std::io::Write,
+    status: &Status,
+) -> anyhow::Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        return Err(Error::Validation(
+            "Confirmation requires a terminal. Use --yes to reset anyway."
+                .to_string(),
+        )
+        .into());
+    }
+
+    write!(
+        output,
+        "Reset {} {} to factory settings? [y/N] ",
+        status.device.name(),
+        status.serial
+    )?;
+    output.flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Save the status, including the configuration, to the user data directory.
+fn backup(status: &Status) -> anyhow::Result<PathBuf> {
+    let dir = dirs::data_dir()
+        .context("Could not find the user data directory.")?
+        .join("umi")
+        .join("backups");
+    std::fs::create_dir_all(&dir)?;
+
+    let timestamp = humantime::format_rfc3339_seconds(SystemTime::now())
+        .to_string()
+        .replace(':', "");
+    let path =
+        dir.join(format!("gateway-{}-{}.json", status.serial, timestamp));
+    std::fs::write(&path, serde_json::to_vec_pretty(status)?)?;
+
+    Ok(path)
+}
+
+/// Check the same gateway came back with factory settings.
+fn check_factory(before: &Status, after: &Status) -> umi::error::Result<()> {
+    if after.serial.to_string() != before.serial.to_string() {
+        return Err(Error::Validation(format!(
+            "A different gateway ({}) answered after the reset.",
+            after.serial
+        )));
+    }
+
+    if after.dhcp != FACTORY_DHCP {
+        return Err(Error::Validation(
+            "Gateway did not return to factory settings: DHCP is disabled."
+                .to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Show what was verified after the reset, and the settings that have no
+/// known factory value so can only be shown.
+fn report(mut output: impl std::io::Write, after: &Status) {
+    write_with_header(
+        &mut output,
+        "Verified".green(),
+        "same serial number, DHCP enabled",
+    );
+    write_with_header(
+        &mut output,
+        "IPv4 Address".green(),
+        &after.ipv4_address.to_string(),
+    );
+
+    let mut unverified = "The IPv4 address has no known factory value.";
+    if let Some(can) = &after.can {
+        write_with_header(
+            &mut output,
+            "CAN Bitrate".green(),
+            &format!(
+                "{} bit/s nominal, {} bit/s data",
+                can.bitrate_nominal, can.bitrate_data
+            ),
+        );
+        unverified =
+            "The IPv4 address and CAN bitrates have no known factory values.";
+    }
+
+    write_with_header(&mut output, "Not Verified".yellow(), unverified);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::gateway::status;
+    use umi::gateway::client::DeviceIdentifier;
+
+    fn status(seq: u16, dhcp: bool) -> Status {
+        let mut status = status::sample(DeviceIdentifier::Serial);
+        status.serial.seq = seq;
+        status.dhcp = dhcp;
+        status
+    }
+
+    #[test]
+    fn factory_settings() {
+        assert!(check_factory(&status(1, false), &status(1, true)).is_ok());
+        assert!(check_factory(&status(1, false), &status(1, false)).is_err());
+        assert!(check_factory(&status(1, false), &status(2, true)).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use super::Target;
+use crate::write_with_header;
+use clap::Parser;
+use colored::Colorize;
+use std::time::{Duration, Instant};
+use umi::{error::Error, gateway::client::Client};
+
+/// How long a gateway may take to come back after a restart.
+pub const READY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Interval between connection attempts while waiting.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Interval between checks that the gateway has gone down, short enough not
+/// to miss a quick restart.
+const OFFLINE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Consecutive failed checks before the gateway counts as offline, so a
+/// dropped packet isn't mistaken for a restart.
+const OFFLINE_PROBES: u32 = 3;
+
+#[derive(Parser, Default)]
+pub struct RestartOptions {
+    /// Wait until the gateway is ready again, giving up if it is not back
+    /// within a timeout, e.g. "30s" or "2m". Defaults to 30s.
+    #[clap(long, value_name = "TIMEOUT", num_args = 0..=1, default_missing_value = "30s", value_parser = humantime::parse_duration)]
+    wait: Option<Duration>,
+}
+
+pub async fn command(
+    mut output: impl std::io::Write,
+    options: RestartOptions,
+    target: &Target,
+) -> anyhow::Result<()> {
+    let mut client = target.connect().await?;
+
+    let Some(timeout) = options.wait else {
+        return run(output, &mut client).await;
+    };
+
+    write_with_header(&mut output, "Restarting".green(), " ");
+    // the gateway may not respond before it goes down.
+    let _ = client.restart().await;
+    drop(client);
+
+    let offline = wait_offline(target, timeout).await?;
+    write_with_header(&mut output, "Offline".green(), " ");
+
+    reconnect(target, timeout).await?;
+    let downtime = Duration::from_millis(offline.elapsed().as_millis() as u64);
+    write_with_header(
+        &mut output,
+        "Ready".green(),
+        &format!("after {} offline", humantime::format_duration(downtime)),
+    );
+
+    Ok(())
+}
+
+/// Restart over an open connection.
+pub async fn run(
+    mut output: impl std::io::Write,
+    client: &mut Client,
+) -> anyhow::Result<()> {
+    write_with_header(&mut output, "Restarting".green(), " ");
+    // the gateway may not respond before it goes down.
+    let _ = client.restart().await;
+    write_with_header(&mut output, "Done".green(), " ");
+
+    Ok(())
+}
+
+/// Connect and check the gateway answers a request.
+///
+/// Requests are not retried so every attempt reflects the current state.
+async fn probe(target: &Target) -> umi::error::Result<Client> {
+    let mut target = target.clone();
+    target.options.retries = 0;
+
+    let mut client = target.connect().await?;
+    client.device_identifier().await?;
+
+    Ok(client)
+}
+
+/// Wait until the gateway stops answering requests.
+///
+/// Returns when it went offline.
+pub async fn wait_offline(
+    target: &Target,
+    timeout: Duration,
+) -> umi::error::Result<Instant> {
+    let start = Instant::now();
+    let mut failures = 0;
+    let mut offline = start;
+
+    while start.elapsed() < timeout {
+        if probe(target).await.is_ok() {
+            failures = 0;
+        } else {
+            if failures == 0 {
+                offline = Instant::now();
+            }
+            failures += 1;
+            if failures == OFFLINE_PROBES {
+                return Ok(offline);
+            }
+        }
+
+        tokio::time::sleep(OFFLINE_POLL_INTERVAL).await;
+    }
+
+    Err(Error::Timeout(format!(
+        "Gateway was still answering {} after the request, it may not have restarted.",
+        humantime::format_duration(timeout)
+    )))
+}
+
+/// Wait until the gateway answers requests again.
+pub async fn reconnect(
+    target: &Target,
+    timeout: Duration,
+) -> umi::error::Result<Client> {
+    let start = Instant::now();
+
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+
+        match probe(target).await {
+            Ok(client) => return Ok(client),
+            Err(_) if start.elapsed() < timeout => continue,
+            Err(err) => {
+                return Err(Error::Timeout(format!(
+                    "Gateway did not come back within {}: {}",
+                    humantime::format_duration(timeout),
+                    err
+                )))
+            }
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use super::{config, restart, status, Target};
+use crate::write_with_header;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use colored::Colorize;
+use rustyline::{
+    completion::Completer, error::ReadlineError, history::FileHistory, Context,
+    Editor, Helper, Highlighter, Hinter, Validator,
+};
+use std::path::PathBuf;
+use umi::{
+    error::Error,
+    gateway::{client::Client, status::Status},
+};
+
+/// Line entered at the shell prompt.
+#[derive(Parser)]
+#[command(name = "shell", no_binary_name = true)]
+struct Line {
+    #[clap(subcommand)]
+    command: ShellCommand,
+}
+
+#[derive(Subcommand)]
+enum ShellCommand {
+    /// Show status
+    Status,
+    /// Read and write configuration
+    Config(config::Cmd),
+    /// Read raw registers or coils
+    Read(ReadOptions),
+    /// Restart
+    Restart,
+    /// Leave the shell
+    #[command(alias = "quit")]
+    Exit,
+}
+
+#[derive(Parser)]
+struct ReadOptions {
+    /// Modbus table to read from.
+    table: Table,
+    /// Address of the first register or coil.
+    address: u16,
+    /// Number of registers or coils to read.
+    #[arg(default_value_t = 1)]
+    count: u16,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Table {
+    Holding,
+    Input,
+    Coils,
+}
+
+pub async fn command(
+    mut output: impl std::io::Write,
+    target: &Target,
+) -> anyhow::Result<()> {
+    let mut client = Some(target.connect().await?);
+
+    let mut editor = Editor::<Completion, FileHistory>::new()?;
+    editor.set_helper(Some(Completion::default()));
+
+    let history = history_path();
+    if let Some(path) = &history {
+        // no history yet on first use.
+        let _ = editor.load_history(path);
+    }
+
+    let prompt = format!("{}> ", target.name);
+
+    loop {
+        let line =
+            match tokio::task::block_in_place(|| editor.readline(&prompt)) {
+                Ok(line) => line,
+                // clear the line and prompt again.
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
+        }
+
+        let command = match Line::try_parse_from(words) {
+            Ok(line) => line.command,
+            Err(err) => {
+                let _ = err.print();
+                continue;
+            }
+        };
+
+        let _ = editor.add_history_entry(line.as_str());
+
+        if let ShellCommand::Exit = command {
+            break;
+        }
+
+        if let Err(err) =
+            execute(&mut output, command, target, &mut client).await
+        {
+            write_with_header(
+                std::io::stderr(),
+                "Error".red(),
+                &err.to_string(),
+            );
+        }
+    }
+
+    if let Some(path) = &history {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        editor.save_history(path)?;
+    }
+
+    Ok(())
+}
+
+/// Run a command, reconnecting first if the connection was lost.
+async fn execute(
+    mut output: impl std::io::Write,
+    command: ShellCommand,
+    target: &Target,
+    client: &mut Option<Client>,
+) -> anyhow::Result<()> {
+    let mut connected = match client.take() {
+        Some(client) => client,
+        None => target.connect().await?,
+    };
+
+    let result = match command {
+        ShellCommand::Status => Status::read(&mut connected)
+            .await
+            .map(|status| {
+                target.remember(&status);
+                status::write(&mut output, &status)
+            })
+            .map_err(Into::into),
+        ShellCommand::Config(command) => {
+            command.apply(&mut output, &mut connected).await
+        }
+        ShellCommand::Read(options) => {
+            read(&mut output, options, &mut connected).await
+        }
+        ShellCommand::Restart => {
+            // the gateway drops the connection as it goes down.
+            return restart::run(&mut output, &mut connected).await;
+        }
+        ShellCommand::Exit => Ok(()),
+    };
+
+    // keep the connection unless it has gone bad.
+    let lost = result.as_ref().is_err_and(|err| {
+        matches!(
+            err.downcast_ref(),
+            Some(Error::Unreachable(_) | Error::Timeout(_))
+        )
+    });
+    if !lost {
+        *client = Some(connected);
+    }
+
+    result
+}
+
+/// Read raw registers or coils and write one line per address.
+async fn read(
+    mut output: impl std::io::Write,
+    options: ReadOptions,
+    client: &mut Client,
+) -> anyhow::Result<()> {
+    let ReadOptions {
+        table,
+        address,
+        count,
+    } = options;
+
+    let values: Vec<String> = match table {
+        Table::Holding => client
+            .read_holding(address, count)
+            .await?
+            .into_iter()
+            .map(|v| format!("{v:#06x} ({v})"))
+            .collect(),
+        Table::Input => client
+            .read_input(address, count)
+            .await?
+            .into_iter()
+            .map(|v| format!("{v:#06x} ({v})"))
+            .collect(),
+        Table::Coils => client
+            .read_coils(address, count)
+            .await?
+            .into_iter()
+            .map(|v| if v { "on" } else { "off" }.to_string())
+            .collect(),
+    };
+
+    for (offset, value) in values.iter().enumerate() {
+        let address = u32::from(address) + offset as u32;
+        write_with_header(&mut output, address.to_string().green(), value);
+    }
+
+    Ok(())
+}
+
+/// Shell history file in the user data directory.
+fn history_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("umi").join("shell_history"))
+}
+
+/// Tab completion of command names and argument values.
+#[derive(Helper, Hinter, Highlighter, Validator)]
+struct Completion {
+    command: clap::Command,
+}
+
+impl Default for Completion {
+    fn default() -> Self {
+        let mut command = Line::command();
+        command.build();
+        Self { command }
+    }
+}
+
+impl Completer for Completion {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let line = &line[..pos];
+        let start = line.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let (before, word) = line.split_at(start);
+
+        Ok((start, candidates(&self.command, before, word)))
+    }
+}
+
+/// Completions for the word being typed after the preceding words.
+fn candidates(
+    command: &clap::Command,
+    before: &str,
+    word: &str,
+) -> Vec<String> {
+    let mut command = command;
+    let mut positional = 0;
+
+    for w in before.split_whitespace() {
+        match command.find_subcommand(w) {
+            Some(subcommand) if positional == 0 => command = subcommand,
+            _ if w.starts_with('-') => {}
+            _ => positional += 1,
+        }
+    }
+
+    let names: Vec<String> = if command.has_subcommands() {
+        if positional > 0 {
+            return Vec::new();
+        }
+        command
+            .get_subcommands()
+            .flat_map(|c| {
+                std::iter::once(c.get_name()).chain(c.get_all_aliases())
+            })
+            .map(String::from)
+            .collect()
+    } else {
+        command
+            .get_positionals()
+            .nth(positional)
+            .map(|arg| {
+                arg.get_possible_values()
+                    .iter()
+                    .map(|v| v.get_name().to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    names.into_iter().filter(|n| n.starts_with(word)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completion() {
+        let command = Completion::default().command;
+
+        assert_eq!(candidates(&command, "", "re"), ["read", "restart"]);
+        assert_eq!(candidates(&command, "config ", "d"), ["dhcp"]);
+        assert_eq!(candidates(&command, "read ", "c"), ["coils"]);
+        assert!(candidates(&command, "read holding ", "").is_empty());
+    }
+
+    #[test]
+    fn parse_lines() {
+        let line = Line::try_parse_from(["read", "holding", "2001", "2"]);
+        assert!(matches!(
+            line.map(|l| l.command),
+            Ok(ShellCommand::Read(ReadOptions {
+                table: Table::Holding,
+                address: 2001,
+                count: 2
+            }))
+        ));
+        assert!(Line::try_parse_from(["read", "discrete", "1"]).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use super::Target;
+use crate::{cli::config::Config, write_with_header};
+use colored::Colorize;
+use std::time::Instant;
+use tracing::debug;
+use umi::gateway::{
+    client::{Client, Version},
+    manifest::Manifest,
+    status::Status,
+};
+
+/// Write the status as headed lines.
+pub fn write(mut output: impl std::io::Write, status: &Status) {
+    for (_, label, value) in status.fields() {
+        write_with_header(&mut output, label.green(), &value);
+    }
+}
+
+/// Read the status, connecting first if needed.
+///
+/// The connection is dropped on error so the next poll reconnects.
+pub async fn poll(
+    target: &Target,
+    client: &mut Option<Client>,
+) -> umi::error::Result<Status> {
+    let mut connected = match client.take() {
+        Some(client) => client,
+        None => target.connect().await?,
+    };
+
+    let status = Status::read(&mut connected).await?;
+    *client = Some(connected);
+
+    Ok(status)
+}
+
+pub async fn command(
+    mut output: impl std::io::Write,
+    target: &Target,
+) -> anyhow::Result<()> {
+    let mut client = target.connect().await?;
+
+    let start = Instant::now();
+    let status = Status::read(&mut client).await?;
+    let elapsed = start.elapsed();
+    target.remember(&status);
+
+    write(&mut output, &status);
+
+    // the hint is best-effort, the status is still useful offline.
+    match update_available(target, &status.firmware_version).await {
+        Ok(Some(hint)) => {
+            write_with_header(&mut output, "Update".yellow(), &hint)
+        }
+        Ok(None) => {}
+        Err(err) => debug!(%err, "could not check for updates"),
+    }
+
+    writeln!(output, "Got status in {:?}", elapsed)?;
+
+    Ok(())
+}
+
+/// Describe a firmware update in the device's release channel, if any.
+async fn update_available(
+    target: &Target,
+    current: &Version,
+) -> anyhow::Result<Option<String>> {
+    let channel = Config::load()?.gateway.channel(&target.name);
+    let manifest =
+        tokio::time::timeout(target.options.timeout, Manifest::fetch())
+            .await??;
+
+    Ok(manifest
+        .update_available(&channel, current)?
+        .map(|version| {
+            format!(
+                "{} is available in the {} channel. Run `umi gateway {} update` to install it.",
+                version, channel, target.name
+            )
+        }))
+}
+
+/// Status of a gateway on the bench, for tests.
+///
+/// Gateways with a CAN interface report CAN settings and counters.
+#[cfg(test)]
+pub fn sample(device: umi::gateway::client::DeviceIdentifier) -> Status {
+    use std::net::Ipv4Addr;
+    use umi::gateway::{
+        client::{DeviceIdentifier, Serial},
+        status::CanStatus,
+    };
+
+    Status {
+        device,
+        serial: Serial {
+            year: 24,
+            week: 12,
+            seq: 0xAB,
+        },
+        hardware_version: Version {
+            major: 1,
+            minor: 0,
+            patch: 0,
+        },
+        firmware_version: Version {
+            major: 0,
+            minor: 2,
+            patch: 0,
+        },
+        dhcp: true,
+        ipv4_address: Ipv4Addr::new(192, 168, 1, 50),
+        can: matches!(device, DeviceIdentifier::CanFd).then_some(CanStatus {
+            bitrate_nominal: 500_000,
+            bitrate_data: 2_000_000,
+            receive_errors: 3,
+            transmit_errors: 0,
+        }),
+    }
+}
@@ -0,0 +1,236 @@
+use super::{Target, UpdateOptions};
+use crate::{
+    cli::{config::Config, inventory},
+    write_with_header,
+};
+use colored::Colorize;
+use serde::Serialize;
+use std::net::IpAddr;
+use umi::{
+    channel::Channel,
+    error::Error,
+    gateway::{
+        manifest::{FirmwareBinary, Manifest},
+        uf2,
+    },
+};
+
+/// Look up a firmware version in the manifest.
+pub fn find_binary<'a>(
+    manifest: &'a Manifest,
+    version: &str,
+) -> umi::error::Result<(&'a String, &'a FirmwareBinary)> {
+    manifest.binaries.get_key_value(version).ok_or_else(|| {
+        Error::Firmware(format!(
+            "Firmware version {} was not found. Run `umi gateway firmware list` to see available versions.",
+            version
+        ))
+    })
+}
+
+/// Refuse to flash after a failed compatibility check.
+fn refuse(err: Error) -> Error {
+    Error::Unsupported(format!("{} Use --force to flash anyway.", err))
+}
+
+/// Firmware update progress.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum Progress {
+    Starting,
+    Warning { message: String },
+    Downloading { version: String },
+    Loading { block: u32, total_blocks: u32 },
+    Done { version: String },
+    Failed { error: String },
+}
+
+impl Progress {
+    pub fn finished(&self) -> bool {
+        matches!(self, Self::Done { .. } | Self::Failed { .. })
+    }
+}
+
+/// IP address of a gateway that can be updated.
+pub fn require_network(target: &Target) -> umi::error::Result<IpAddr> {
+    target.ip().ok_or_else(|| {
+        Error::Unsupported(
+            "Firmware updates require a network connection to the gateway."
+                .to_string(),
+        )
+    })
+}
+
+/// Firmware to install.
+pub enum Firmware {
+    /// Version from the manifest.
+    ///
+    /// Uses the channel set in the config file unless a version or channel
+    /// is given.
+    Release {
+        version: Option<String>,
+        channel: Option<Channel>,
+    },
+    /// Local firmware file.
+    File { name: String, contents: Vec<u8> },
+}
+
+/// Update the gateway firmware.
+///
+/// Returns the installed version, or the file name for a local file.
+pub async fn install(
+    target: &Target,
+    firmware: Firmware,
+    force: bool,
+    mut progress: impl FnMut(Progress),
+) -> anyhow::Result<String> {
+    let ip = require_network(target)?;
+
+    // forced updates skip the checks, and with them the reads they need.
+    let mut checked = None;
+    if !force {
+        let mut client = target.connect().await?;
+        let device = client.device_identifier().await?;
+        let hardware = client.hardware_version().await?;
+        let mut record = target.record(&client.serial().await?);
+        record.device_type = Some(device.code());
+        record.hardware_version = Some(hardware.to_string());
+        checked = Some((device, hardware, record));
+    }
+
+    let mut warn = |message: String| progress(Progress::Warning { message });
+
+    let (version, binary) = match firmware {
+        Firmware::File { name, contents } => {
+            if let Some((device, ..)) = &checked {
+                uf2::check_family(&contents, *device).map_err(refuse)?;
+                warn(format!(
+                    "The device type of a local firmware file can't be verified, make sure it is for {} gateways.",
+                    device.code()
+                ));
+            }
+
+            (name, contents)
+        }
+        Firmware::Release { version, channel } => {
+            let manifest = Manifest::fetch().await?;
+            let version = match version {
+                Some(version) => version,
+                None => {
+                    let channel = match channel {
+                        Some(channel) => channel,
+                        None => Config::load()?.gateway.channel(&target.name),
+                    };
+                    manifest.channel_version(&channel)?.to_string()
+                }
+            };
+
+            let (version, firmware) = find_binary(&manifest, &version)?;
+            if let Some((device, hardware, record)) = &mut checked {
+                firmware
+                    .check_compatible(*device, hardware)
+                    .map_err(refuse)?;
+                record.firmware_version = Some(version.clone());
+            }
+
+            progress(Progress::Downloading {
+                version: version.clone(),
+            });
+            let binary = firmware.download().await?;
+
+            (version.clone(), binary)
+        }
+    };
+
+    uf2::upload(
+        ip,
+        target.options.timeout,
+        &binary,
+        |block, total_blocks| {
+            progress(Progress::Loading {
+                block,
+                total_blocks,
+            })
+        },
+    )
+    .await?;
+
+    // the version of a local file is not known.
+    if let Some((.., record)) = checked {
+        if record.firmware_version.is_some() {
+            inventory::remember([record]);
+        }
+    }
+
+    Ok(version)
+}
+
+pub async fn command(
+    mut output: impl std::io::Write,
+    options: UpdateOptions,
+    target: &Target,
+) -> anyhow::Result<()> {
+    let firmware = match options.file {
+        Some(path) => {
+            writeln!(output, "Reading firmware file.")?;
+            Firmware::File {
+                name: path.display().to_string(),
+                contents: tokio::fs::read(&path).await?,
+            }
+        }
+        None => {
+            let channel = match options.version {
+                Some(_) => None,
+                None => {
+                    let mut config = Config::load()?;
+                    let channel = match options.channel {
+                        Some(channel) => {
+                            if options.persist {
+                                config
+                                    .gateway
+                                    .set_channel(&target.name, channel.clone());
+                                config.save()?;
+                            }
+                            channel
+                        }
+                        None => config.gateway.channel(&target.name),
+                    };
+
+                    write_with_header(
+                        &mut output,
+                        "Channel".green(),
+                        &channel.to_string(),
+                    );
+
+                    Some(channel)
+                }
+            };
+
+            Firmware::Release {
+                version: options.version,
+                channel,
+            }
+        }
+    };
+
+    install(target, firmware, options.force, |progress| match progress {
+        Progress::Warning { message } => {
+            write_with_header(&mut output, "Warning".yellow(), &message)
+        }
+        Progress::Downloading { version } => {
+            write_with_header(&mut output, "Downloading".green(), &version)
+        }
+        Progress::Loading { block: 0, .. } => {
+            write_with_header(&mut output, "Erasing".green(), "...")
+        }
+        Progress::Loading { block: 1, .. } => {
+            write_with_header(&mut output, "Loading".green(), "...")
+        }
+        _ => {}
+    })
+    .await?;
+
+    write_with_header(&mut output, "Done".green(), " ");
+
+    Ok(())
+}
@@ -0,0 +1,188 @@
+//! Status watching.
+
+use super::{status::poll, Target};
+use crate::write_with_header;
+use colored::Colorize;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::time::MissedTickBehavior;
+use umi::gateway::status::Status;
+
+/// Number of connectivity events kept on screen.
+const EVENT_HISTORY: usize = 5;
+
+pub async fn command(
+    mut output: impl std::io::Write,
+    target: &Target,
+    interval: Duration,
+) -> anyhow::Result<()> {
+    let json = !std::io::stdout().is_terminal();
+
+    let mut client = None;
+    let mut previous = HashMap::new();
+    let mut events = Vec::new();
+    // when the device was last seen going offline, none while online.
+    let mut offline_since = None;
+
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+
+        let result = poll(target, &mut client).await;
+        let now = SystemTime::now();
+
+        let event = match (&result, offline_since) {
+            (Ok(_), Some(since)) => {
+                offline_since = None;
+                let downtime = Instant::now().duration_since(since);
+                Some(Event::Reconnected(downtime))
+            }
+            (Err(err), None) => {
+                offline_since = Some(Instant::now());
+                Some(Event::Disconnected(err.to_string()))
+            }
+            _ => None,
+        };
+
+        if json {
+            if let Some(event) = &event {
+                writeln!(output, "{}", event.to_json(now))?;
+            }
+
+            if let Ok(status) = &result {
+                let line = StatusLine {
+                    event: "status",
+                    timestamp: timestamp(now),
+                    status,
+                };
+                writeln!(output, "{}", serde_json::to_string(&line)?)?;
+            }
+
+            output.flush()?;
+            continue;
+        }
+
+        if let Some(event) = event {
+            events.push(format!(
+                "{} {}",
+                humantime::format_rfc3339_seconds(now),
+                event
+            ));
+            if events.len() > EVENT_HISTORY {
+                events.remove(0);
+            }
+        }
+
+        // clear the screen and move to the top left.
+        write!(output, "\x1b[2J\x1b[H")?;
+
+        write_with_header(
+            &mut output,
+            "Watching".green(),
+            &format!(
+                "{} every {}, press Ctrl-C to stop",
+                target.name,
+                humantime::format_duration(interval)
+            ),
+        );
+
+        match &result {
+            Ok(status) => {
+                write_with_header(&mut output, "Connection".green(), "online");
+
+                for (key, label, value) in status.fields() {
+                    let changed = previous
+                        .get(key)
+                        .is_some_and(|previous| previous != &value);
+                    let value = if changed {
+                        value.yellow().bold().to_string()
+                    } else {
+                        value.clone()
+                    };
+                    write_with_header(&mut output, label.green(), &value);
+                }
+
+                previous = status
+                    .fields()
+                    .into_iter()
+                    .map(|(key, _, value)| (key, value))
+                    .collect();
+            }
+            Err(err) => {
+                write_with_header(
+                    &mut output,
+                    "Connection".red(),
+                    &format!("offline: {}", err),
+                );
+            }
+        }
+
+        if !events.is_empty() {
+            write_with_header(
+                &mut output,
+                "Events".green(),
+                &events.join("\n"),
+            );
+        }
+
+        output.flush()?;
+    }
+}
+
+#[derive(Serialize)]
+struct StatusLine<'a> {
+    event: &'static str,
+    timestamp: u64,
+    #[serde(flatten)]
+    status: &'a Status,
+}
+
+/// Connectivity change.
+enum Event {
+    Disconnected(String),
+    Reconnected(Duration),
+}
+
+impl Event {
+    fn to_json(&self, now: SystemTime) -> Value {
+        match self {
+            Self::Disconnected(err) => json!({
+                "event": "disconnected",
+                "timestamp": timestamp(now),
+                "error": err,
+            }),
+            Self::Reconnected(downtime) => json!({
+                "event": "reconnected",
+                "timestamp": timestamp(now),
+                "downtime_ms": downtime.as_millis() as u64,
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Disconnected(err) => write!(f, "connection lost: {}", err),
+            Self::Reconnected(downtime) => write!(
+                f,
+                "connection recovered after {}",
+                humantime::format_duration(Duration::from_secs(
+                    downtime.as_secs()
+                ))
+            ),
+        }
+    }
+}
+
+/// Seconds since the Unix epoch.
+fn timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
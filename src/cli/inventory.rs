@@ -0,0 +1,432 @@
+//! Local inventory of devices the CLI has touched.
+
+use crate::{cli::logging, write_with_header};
+use anyhow::Error;
+use clap::{Parser, Subcommand, ValueEnum};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Display,
+    io::Write,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::warn;
+
+/// Kind of device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    Gateway,
+    Adapter,
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Gateway => write!(f, "gateway"),
+            Self::Adapter => write!(f, "adapter"),
+        }
+    }
+}
+
+/// Inventory record of a single device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub serial: String,
+    pub kind: Kind,
+    /// Device type, e.g. "FD" for Gateways or the product name for Adapters.
+    pub device_type: Option<String>,
+    pub hardware_version: Option<String>,
+    pub firmware_version: Option<String>,
+    /// Last known IP address.
+    pub address: Option<String>,
+    /// Seconds since the Unix epoch.
+    pub first_seen: u64,
+    /// Seconds since the Unix epoch.
+    pub last_seen: u64,
+}
+
+impl Record {
+    /// Create a record of a device seen now.
+    pub fn new(kind: Kind, serial: String) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|t| t.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            serial,
+            kind,
+            device_type: None,
+            hardware_version: None,
+            firmware_version: None,
+            address: None,
+            first_seen: now,
+            last_seen: now,
+        }
+    }
+
+    /// Update with a newer record of the same device.
+    fn merge(&mut self, newer: Record) {
+        self.last_seen = newer.last_seen;
+        self.device_type = newer.device_type.or(self.device_type.take());
+        self.hardware_version =
+            newer.hardware_version.or(self.hardware_version.take());
+        self.firmware_version =
+            newer.firmware_version.or(self.firmware_version.take());
+        self.address = newer.address.or(self.address.take());
+    }
+}
+
+/// Inventory file format.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Inventory {
+    pub devices: Vec<Record>,
+}
+
+impl Inventory {
+    /// Location of the inventory file.
+    pub fn path() -> anyhow::Result<PathBuf> {
+        let dir = dirs::data_dir().ok_or_else(|| {
+            Error::msg("Could not find the user data directory.")
+        })?;
+
+        Ok(dir.join("umi").join("inventory.json"))
+    }
+
+    /// Load the inventory, or an empty one if there is no file.
+    pub fn load() -> anyhow::Result<Self> {
+        let path = Self::path()?;
+
+        match std::fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents).map_err(|err| {
+                Error::msg(format!(
+                    "Invalid inventory {}: {}",
+                    path.display(),
+                    err
+                ))
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                Ok(Self::default())
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Save the inventory.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = Self::path()?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        // write then rename so an interrupted write can't corrupt the file.
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let mut temp = tempfile::NamedTempFile::new_in(dir)?;
+        temp.write_all(&serde_json::to_vec_pretty(self)?)?;
+        temp.persist(path)?;
+
+        Ok(())
+    }
+
+    /// Lock the inventory against other processes until the lock is dropped.
+    ///
+    /// Hold the lock from loading until saving so concurrent runs don't lose
+    /// each other's records.
+    pub fn lock() -> anyhow::Result<std::fs::File> {
+        let path = Self::path()?.with_extension("lock");
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+        file.lock()?;
+
+        Ok(file)
+    }
+
+    /// Add or update a device record.
+    pub fn insert(&mut self, record: Record) {
+        match self
+            .devices
+            .iter_mut()
+            .find(|r| r.kind == record.kind && r.serial == record.serial)
+        {
+            Some(existing) => existing.merge(record),
+            None => self.devices.push(record),
+        }
+    }
+
+    /// Last known IP address of a device.
+    pub fn address(&self, kind: Kind, serial: &str) -> Option<IpAddr> {
+        self.devices
+            .iter()
+            .find(|r| r.kind == kind && r.serial == serial)
+            .and_then(|r| r.address.as_deref()?.parse().ok())
+    }
+
+    /// Find records by serial number.
+    fn find(&self, serial: &str) -> Vec<&Record> {
+        self.devices.iter().filter(|r| r.serial == serial).collect()
+    }
+}
+
+/// Record devices in the inventory.
+///
+/// The inventory is best-effort, so failures are reported as warnings rather
+/// than failing the command. Warnings are logged instead while stderr is
+/// hidden.
+pub fn remember(records: impl IntoIterator<Item = Record>) {
+    let result = Inventory::lock().and_then(|_lock| {
+        let mut inventory = Inventory::load()?;
+        records.into_iter().for_each(|r| inventory.insert(r));
+        inventory.save()
+    });
+
+    match result {
+        Ok(()) => {}
+        Err(err) if logging::stderr_hidden() => {
+            warn!(%err, "could not update inventory")
+        }
+        Err(err) => write_with_header(
+            std::io::stderr(),
+            "Warning".yellow(),
+            &format!("Could not update inventory: {}", err),
+        ),
+    }
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// List known devices.
+    List,
+    /// Show details of a device.
+    Show(Serial),
+    /// Remove a device from the inventory.
+    Forget(Serial),
+    /// Export the inventory.
+    Export(Export),
+}
+
+#[derive(Parser)]
+struct Serial {
+    /// Serial number of the device.
+    serial: String,
+}
+
+#[derive(Parser)]
+struct Export {
+    /// Output format.
+    #[clap(long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+    /// Write to a file instead of stdout.
+    #[clap(long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    Csv,
+}
+
+#[derive(Parser)]
+pub struct Cmd {
+    #[clap(subcommand)]
+    subcommand: Commands,
+}
+
+impl Cmd {
+    pub async fn run(self) -> anyhow::Result<()> {
+        let mut output = std::io::stdout();
+
+        let _lock = Inventory::lock()?;
+        let mut inventory = Inventory::load()?;
+
+        match self.subcommand {
+            Commands::List => {
+                writeln!(
+                    output,
+                    "{0: <12} {1: <8} {2: <16} {3: <10} {4: <16} Last Seen",
+                    "Serial No.", "Kind", "Type", "Firmware", "Address"
+                )?;
+                for r in &inventory.devices {
+                    writeln!(
+                        output,
+                        "{0: <12} {1: <8} {2: <16} {3: <10} {4: <16} {5}",
+                        r.serial,
+                        r.kind.to_string(),
+                        r.device_type.as_deref().unwrap_or("-"),
+                        r.firmware_version.as_deref().unwrap_or("-"),
+                        r.address.as_deref().unwrap_or("-"),
+                        format_time(r.last_seen),
+                    )?;
+                }
+
+                Ok(())
+            }
+            Commands::Show(show) => {
+                let records = inventory.find(&show.serial);
+                if records.is_empty() {
+                    return Err(not_found(&show.serial).into());
+                }
+
+                for r in records {
+                    let field = |v: &Option<String>| {
+                        v.as_deref().unwrap_or("-").to_string()
+                    };
+                    write_with_header(&mut output, "Serial".green(), &r.serial);
+                    write_with_header(
+                        &mut output,
+                        "Kind".green(),
+                        &r.kind.to_string(),
+                    );
+                    write_with_header(
+                        &mut output,
+                        "Type".green(),
+                        &field(&r.device_type),
+                    );
+                    write_with_header(
+                        &mut output,
+                        "Hardware Version".green(),
+                        &field(&r.hardware_version),
+                    );
+                    write_with_header(
+                        &mut output,
+                        "Firmware Version".green(),
+                        &field(&r.firmware_version),
+                    );
+                    write_with_header(
+                        &mut output,
+                        "Address".green(),
+                        &field(&r.address),
+                    );
+                    write_with_header(
+                        &mut output,
+                        "First Seen".green(),
+                        &format_time(r.first_seen),
+                    );
+                    write_with_header(
+                        &mut output,
+                        "Last Seen".green(),
+                        &format_time(r.last_seen),
+                    );
+                }
+
+                Ok(())
+            }
+            Commands::Forget(forget) => {
+                let count = inventory.devices.len();
+                inventory.devices.retain(|r| r.serial != forget.serial);
+                if inventory.devices.len() == count {
+                    return Err(not_found(&forget.serial).into());
+                }
+                inventory.save()?;
+
+                write_with_header(
+                    &mut output,
+                    "Forgotten".green(),
+                    &forget.serial,
+                );
+
+                Ok(())
+            }
+            Commands::Export(export) => {
+                let contents = match export.format {
+                    Format::Json => serde_json::to_string_pretty(&inventory)?,
+                    Format::Csv => to_csv(&inventory),
+                };
+
+                match export.output {
+                    Some(path) => std::fs::write(path, contents)?,
+                    None => writeln!(output, "{}", contents)?,
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+fn not_found(serial: &str) -> umi::error::Error {
+    umi::error::Error::Validation(format!(
+        "Device {} is not in the inventory.",
+        serial
+    ))
+}
+
+/// Format seconds since the Unix epoch as an RFC 3339 timestamp.
+fn format_time(secs: u64) -> String {
+    humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_secs(secs))
+        .to_string()
+}
+
+fn to_csv(inventory: &Inventory) -> String {
+    let mut csv = String::from(
+        "serial,kind,device_type,hardware_version,firmware_version,address,first_seen,last_seen\n",
+    );
+
+    for r in &inventory.devices {
+        let fields = [
+            r.serial.clone(),
+            r.kind.to_string(),
+            r.device_type.clone().unwrap_or_default(),
+            r.hardware_version.clone().unwrap_or_default(),
+            r.firmware_version.clone().unwrap_or_default(),
+            r.address.clone().unwrap_or_default(),
+            format_time(r.first_seen),
+            format_time(r.last_seen),
+        ];
+        csv.push_str(&csv_row(&fields));
+    }
+
+    csv
+}
+
+/// Format fields as a CSV row, quoting where needed.
+pub fn csv_row(fields: &[String]) -> String {
+    let row: Vec<String> = fields
+        .iter()
+        .map(|f| {
+            if f.contains([',', '"', '\n']) {
+                format!("\"{}\"", f.replace('"', "\"\""))
+            } else {
+                f.clone()
+            }
+        })
+        .collect();
+
+    format!("{}\n", row.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_merges() {
+        let mut inventory = Inventory::default();
+
+        let mut first = Record::new(Kind::Gateway, "2412-0001".to_string());
+        first.firmware_version = Some("v0.1.0".to_string());
+        first.address = Some("10.0.0.2".to_string());
+        inventory.insert(first);
+
+        let mut second = Record::new(Kind::Gateway, "2412-0001".to_string());
+        second.firmware_version = Some("v0.2.0".to_string());
+        inventory.insert(second);
+
+        assert_eq!(inventory.devices.len(), 1);
+        let record = &inventory.devices[0];
+        assert_eq!(record.firmware_version.as_deref(), Some("v0.2.0"));
+        assert_eq!(record.address.as_deref(), Some("10.0.0.2"));
+    }
+}
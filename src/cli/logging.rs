@@ -0,0 +1,118 @@
+//! Diagnostic logging.
+//!
+//! Logs are kept separate from command output. They go to stderr, or to a
+//! file when one is given.
+
+use clap::{ArgAction, Args, ValueEnum};
+use std::{
+    fs::File,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::{
+    filter::Targets,
+    fmt::writer::{BoxMakeWriter, MakeWriterExt},
+    prelude::*,
+    registry,
+};
+use umi::error::Error;
+
+/// Whether a full screen interface covers stderr.
+static STDERR_HIDDEN: AtomicBool = AtomicBool::new(false);
+
+/// Hold back logs and warnings on stderr while a full screen interface is
+/// shown.
+pub fn hide_stderr(hidden: bool) {
+    STDERR_HIDDEN.store(hidden, Ordering::Relaxed);
+}
+
+/// Whether logs and warnings on stderr are held back.
+pub fn stderr_hidden() -> bool {
+    STDERR_HIDDEN.load(Ordering::Relaxed)
+}
+
+#[derive(Args)]
+pub struct LogOptions {
+    /// Log more detail, -v for debug and -vv for trace.
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
+    /// Write logs to a file instead of stderr.
+    ///
+    /// Logs debug messages unless more detail is requested.
+    #[arg(long, global = true, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+    /// Log format.
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl LogOptions {
+    /// Install the global logger.
+    pub fn init(&self) -> anyhow::Result<()> {
+        let level = match (self.verbose, &self.log_file) {
+            (0, None) => LevelFilter::WARN,
+            (0 | 1, _) => LevelFilter::DEBUG,
+            _ => LevelFilter::TRACE,
+        };
+
+        // dependencies are only interesting when tracing.
+        let dependencies = match level {
+            LevelFilter::TRACE => LevelFilter::DEBUG,
+            _ => LevelFilter::WARN,
+        };
+
+        let filter = Targets::new()
+            .with_target(env!("CARGO_CRATE_NAME"), level)
+            // DFU state changes are logged by dfu-core.
+            .with_target("dfu_core", level)
+            .with_default(dependencies);
+
+        let (writer, ansi) = match &self.log_file {
+            Some(path) => {
+                let file = File::options()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|err| {
+                        Error::Validation(format!(
+                            "Could not open log file {}: {}",
+                            path.display(),
+                            err
+                        ))
+                    })?;
+                (BoxMakeWriter::new(Mutex::new(file)), false)
+            }
+            None => (
+                BoxMakeWriter::new(
+                    std::io::stderr.with_filter(|_| !stderr_hidden()),
+                ),
+                true,
+            ),
+        };
+
+        let layer = tracing_subscriber::fmt::layer()
+            .with_writer(writer)
+            .with_ansi(ansi);
+
+        let result = match self.log_format {
+            LogFormat::Text => {
+                registry().with(layer.with_filter(filter)).try_init()
+            }
+            LogFormat::Json => {
+                registry().with(layer.json().with_filter(filter)).try_init()
+            }
+        };
+
+        result.map_err(|err| anyhow::Error::msg(err.to_string()))
+    }
+}
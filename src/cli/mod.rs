@@ -0,0 +1,11 @@
+//! Command line interface.
+
+pub mod adapter;
+pub mod config;
+pub mod gateway;
+pub mod inventory;
+pub mod logging;
+pub mod provision;
+pub mod self_update;
+pub mod serve;
+pub mod tui;
@@ -0,0 +1,424 @@
+//! End-of-line provisioning of Gateways.
+//!
+//! Flashes the firmware pinned in a profile, applies its configuration,
+//! verifies everything by reading it back and restarts the gateway. Every
+//! unit gets a pass or fail record appended to a report.
+
+use crate::{
+    cli::config::Config,
+    cli::gateway::{
+        config, restart,
+        update::{self, Firmware, Progress},
+        ConnectionOptions, Target,
+    },
+    cli::inventory::csv_row,
+    write_with_header,
+};
+use anyhow::Context;
+use clap::Parser;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    io::Write,
+    net::Ipv4Addr,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use umi::{error::Error, gateway::status::Status};
+
+#[derive(Parser)]
+pub struct Cmd {
+    /// Provisioning profile.
+    #[clap(long)]
+    profile: PathBuf,
+    /// Report to append a record of each unit to.
+    ///
+    /// Written as CSV if the file name ends in ".csv", otherwise as JSON Lines.
+    #[clap(long, default_value = "provision-report.csv")]
+    report: PathBuf,
+    /// IP address, device name from the config file, serial number of a
+    /// gateway in the inventory or serial port, e.g.
+    /// "serial:/dev/ttyUSB0@115200".
+    #[arg(value_name = "DEVICE")]
+    device: String,
+    #[clap(flatten)]
+    connection: ConnectionOptions,
+}
+
+/// Provisioning profile.
+///
+/// # Example
+///
+/// ```toml
+/// # Firmware version to flash, see `umi gateway firmware list`.
+/// firmware = "v0.3.0"
+/// # Refuse any other device type.
+/// device_type = "FD"
+///
+/// [config]
+/// dhcp = false
+/// ipv4_address = "192.168.1.50"
+/// bitrate_nominal = 500000
+/// bitrate_data = 2000000
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Profile {
+    /// Firmware version to install.
+    firmware: Option<String>,
+    /// Expected device type code, e.g. "FD".
+    device_type: Option<String>,
+    /// Configuration to apply.
+    #[serde(default)]
+    config: Settings,
+}
+
+/// Gateway configuration set by a profile.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Settings {
+    dhcp: Option<bool>,
+    ipv4_address: Option<Ipv4Addr>,
+    bitrate_nominal: Option<u32>,
+    /// Defaults to the nominal bitrate.
+    bitrate_data: Option<u32>,
+}
+
+impl Profile {
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+
+        toml::from_str(&contents).map_err(|err| {
+            Error::Validation(format!(
+                "Invalid profile {}: {}",
+                path.display(),
+                err
+            ))
+            .into()
+        })
+    }
+}
+
+impl Settings {
+    /// Short hash identifying the configuration in reports.
+    fn hash(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        Sha256::digest(json)
+            .iter()
+            .take(8)
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Differences between the configuration and what the gateway reports.
+    fn mismatches(&self, status: &Status) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        let mut check = |name: &str, expected: String, actual: String| {
+            if expected != actual {
+                mismatches.push(format!(
+                    "{} is {}, expected {}",
+                    name, actual, expected
+                ));
+            }
+        };
+
+        if let Some(dhcp) = self.dhcp {
+            check("DHCP", dhcp.to_string(), status.dhcp.to_string());
+        }
+
+        if let Some(ip) = self.ipv4_address {
+            check(
+                "IPv4 address",
+                ip.to_string(),
+                status.ipv4_address.to_string(),
+            );
+        }
+
+        if let Some(nominal) = self.bitrate_nominal {
+            let data = self.bitrate_data.unwrap_or(nominal);
+            let (actual_nominal, actual_data) = match &status.can {
+                Some(can) => (can.bitrate_nominal, can.bitrate_data),
+                None => (0, 0),
+            };
+            check(
+                "Nominal bitrate",
+                nominal.to_string(),
+                actual_nominal.to_string(),
+            );
+            check("Data bitrate", data.to_string(), actual_data.to_string());
+        }
+
+        mismatches
+    }
+}
+
+/// Report record of one unit.
+#[derive(Debug, Default, Serialize)]
+struct Record {
+    timestamp: String,
+    device: String,
+    serial: String,
+    device_type: String,
+    hardware_version: String,
+    firmware_version: String,
+    config_hash: String,
+    /// "pass" or "fail".
+    result: String,
+    error: String,
+}
+
+impl Record {
+    /// Fill in what the gateway reports about itself.
+    fn update(&mut self, status: &Status) {
+        self.serial = status.serial.to_string();
+        self.device_type = status.device.code();
+        self.hardware_version = status.hardware_version.to_string();
+        self.firmware_version = status.firmware_version.to_string();
+    }
+
+    fn to_csv(&self) -> String {
+        csv_row(&[
+            self.timestamp.clone(),
+            self.device.clone(),
+            self.serial.clone(),
+            self.device_type.clone(),
+            self.hardware_version.clone(),
+            self.firmware_version.clone(),
+            self.config_hash.clone(),
+            self.result.clone(),
+            self.error.clone(),
+        ])
+    }
+
+    /// Append the record to a report file.
+    fn append(&self, path: &Path) -> anyhow::Result<()> {
+        let csv = path.extension().is_some_and(|ext| ext == "csv");
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Could not open {}", path.display()))?;
+
+        if csv {
+            if file.metadata()?.len() == 0 {
+                file.write_all(b"timestamp,device,serial,device_type,hardware_version,firmware_version,config_hash,result,error\n")?;
+            }
+            file.write_all(self.to_csv().as_bytes())?;
+        } else {
+            writeln!(file, "{}", serde_json::to_string(self)?)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Cmd {
+    pub async fn run(self) -> anyhow::Result<()> {
+        let mut output = std::io::stdout();
+
+        let profile = Profile::load(&self.profile)?;
+        let target = Target::resolve(
+            &self.device,
+            &Config::load()?.gateway,
+            &self.connection,
+        )?;
+
+        let mut record = Record {
+            timestamp: humantime::format_rfc3339_seconds(SystemTime::now())
+                .to_string(),
+            device: self.device.clone(),
+            config_hash: profile.config.hash(),
+            ..Default::default()
+        };
+
+        let result =
+            provision(&mut output, &profile, &target, &mut record).await;
+
+        record.result = match &result {
+            Ok(()) => "pass".to_string(),
+            Err(err) => {
+                record.error = err.to_string();
+                "fail".to_string()
+            }
+        };
+        record.append(&self.report)?;
+
+        let unit = match record.serial.as_str() {
+            "" => record.device.clone(),
+            serial => format!("{} ({})", record.device, serial),
+        };
+
+        match result {
+            Ok(()) => {
+                write_with_header(&mut output, "PASS".green(), &unit);
+                Ok(())
+            }
+            Err(err) => {
+                write_with_header(&mut output, "FAIL".red(), &unit);
+                Err(err)
+            }
+        }
+    }
+}
+
+async fn provision(
+    mut output: impl std::io::Write,
+    profile: &Profile,
+    target: &Target,
+    record: &mut Record,
+) -> anyhow::Result<()> {
+    let mut client = target.connect().await?;
+
+    let status = Status::read(&mut client).await?;
+    target.remember(&status);
+    record.update(&status);
+    write_with_header(
+        &mut output,
+        "Device".green(),
+        &format!("{} ({})", status.device.name(), status.serial),
+    );
+
+    if let Some(expected) = &profile.device_type {
+        if status.device.code() != *expected {
+            return Err(Error::Unsupported(format!(
+                "Device type is {}, the profile requires {}.",
+                status.device.code(),
+                expected
+            ))
+            .into());
+        }
+    }
+
+    if let Some(version) = &profile.firmware {
+        if status.firmware_version.to_string() == *version {
+            write_with_header(
+                &mut output,
+                "Firmware".green(),
+                &format!("{} already installed", version),
+            );
+        } else {
+            drop(client);
+            write_with_header(&mut output, "Firmware".green(), version);
+
+            update::install(
+                target,
+                Firmware::Release {
+                    version: Some(version.clone()),
+                    channel: None,
+                },
+                false,
+                |progress| match progress {
+                    Progress::Downloading { .. } => write_with_header(
+                        &mut output,
+                        "Downloading".green(),
+                        " ",
+                    ),
+                    Progress::Loading { block: 0, .. } => {
+                        write_with_header(&mut output, "Erasing".green(), "...")
+                    }
+                    Progress::Loading { block: 1, .. } => {
+                        write_with_header(&mut output, "Loading".green(), "...")
+                    }
+                    _ => {}
+                },
+            )
+            .await?;
+
+            write_with_header(&mut output, "Waiting".green(), "for restart");
+            restart::wait_offline(target, restart::READY_TIMEOUT).await?;
+            client = restart::reconnect(target, restart::READY_TIMEOUT).await?;
+        }
+    }
+
+    let settings = &profile.config;
+    if let Some(enable) = settings.dhcp {
+        client.set_dhcp(enable).await?;
+    }
+    if let Some(ip) = settings.ipv4_address {
+        client.set_ipv4_address(ip).await?;
+    }
+    if let Some(nominal) = settings.bitrate_nominal {
+        config::require_can(&mut client).await?;
+        let data = settings.bitrate_data.unwrap_or(nominal);
+        config::set_bitrate(&mut client, nominal, data).await?;
+    }
+    write_with_header(&mut output, "Configured".green(), " ");
+
+    let status = Status::read(&mut client).await?;
+    target.remember(&status);
+    record.update(&status);
+
+    if let Some(version) = &profile.firmware {
+        if status.firmware_version.to_string() != *version {
+            return Err(Error::Firmware(format!(
+                "Verification failed: firmware version is {}, expected {}.",
+                status.firmware_version, version
+            ))
+            .into());
+        }
+    }
+    let mismatches = settings.mismatches(&status);
+    if !mismatches.is_empty() {
+        return Err(Error::Validation(format!(
+            "Verification failed: {}.",
+            mismatches.join("; ")
+        ))
+        .into());
+    }
+    write_with_header(&mut output, "Verified".green(), " ");
+
+    restart::run(&mut output, &mut client).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::gateway::status;
+    use umi::gateway::client::DeviceIdentifier;
+
+    #[test]
+    fn profile() {
+        let profile: Profile = toml::from_str(
+            r#"
+            firmware = "v0.3.0"
+
+            [config]
+            dhcp = false
+            bitrate_nominal = 500000
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(profile.firmware.as_deref(), Some("v0.3.0"));
+        assert_eq!(profile.config.dhcp, Some(false));
+        assert!(toml::from_str::<Profile>("firmwre = \"v0.3.0\"").is_err());
+    }
+
+    #[test]
+    fn verify_settings() {
+        let settings = Settings {
+            dhcp: Some(false),
+            bitrate_nominal: Some(500_000),
+            bitrate_data: Some(2_000_000),
+            ..Default::default()
+        };
+        let mut status = status::sample(DeviceIdentifier::CanFd);
+        status.dhcp = false;
+        if let Some(can) = status.can.as_mut() {
+            can.bitrate_data = 500_000;
+        }
+
+        assert_eq!(
+            settings.mismatches(&status),
+            ["Data bitrate is 500000, expected 2000000"]
+        );
+
+        if let Some(can) = status.can.as_mut() {
+            can.bitrate_data = 2_000_000;
+        }
+        assert!(settings.mismatches(&status).is_empty());
+    }
+}
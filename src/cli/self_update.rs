@@ -0,0 +1,230 @@
+//! Update the CLI itself.
+
+use crate::write_with_header;
+use anyhow::Error;
+use clap::{Parser, Subcommand};
+use colored::Colorize;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::io::{Read, Seek, SeekFrom, Write};
+use umi::http::{get, get_bytes};
+
+/// Latest release of the CLI.
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/umi-eng/cli/releases/latest";
+
+/// Target triple the CLI was built for.
+const TARGET: &str = env!("TARGET");
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Check for a newer version.
+    Check,
+    /// Update to the latest version.
+    Update(UpdateOptions),
+}
+
+#[derive(Parser)]
+struct UpdateOptions {
+    /// Reinstall even if already up to date.
+    #[clap(long)]
+    force: bool,
+}
+
+#[derive(Parser)]
+pub struct Cmd {
+    #[clap(subcommand)]
+    subcommand: Commands,
+}
+
+impl Cmd {
+    pub async fn run(self) -> anyhow::Result<()> {
+        let mut output = std::io::stdout();
+
+        write_with_header(&mut output, "Checking".green(), " ");
+        let release = get(LATEST_RELEASE_URL).await?.json::<Release>().await?;
+
+        let current = semver::Version::parse(env!("CARGO_PKG_VERSION"))?;
+        let latest =
+            semver::Version::parse(release.tag_name.trim_start_matches('v'))?;
+
+        write_with_header(
+            &mut output,
+            "Current".green(),
+            &format!("v{}", current),
+        );
+        write_with_header(
+            &mut output,
+            "Latest".green(),
+            &format!("v{}", latest),
+        );
+
+        match self.subcommand {
+            Commands::Check => {
+                if latest > current {
+                    write_with_header(
+                        &mut output,
+                        "Update available".yellow(),
+                        "Run `umi self update` to install.",
+                    );
+                } else {
+                    write_with_header(&mut output, "Up to date".green(), " ");
+                }
+
+                Ok(())
+            }
+            Commands::Update(options) => {
+                if latest <= current && !options.force {
+                    write_with_header(&mut output, "Up to date".green(), " ");
+                    return Ok(());
+                }
+
+                update(output, &release).await
+            }
+        }
+    }
+}
+
+async fn update(
+    mut output: impl std::io::Write,
+    release: &Release,
+) -> anyhow::Result<()> {
+    let archive_name = format!("umi-{}{}", TARGET, ARCHIVE_EXTENSION);
+    let checksum_name = format!("{}.sha256", archive_name);
+
+    let find = |name: &str| {
+        release
+            .assets
+            .iter()
+            .find(|a| a.name == name)
+            .ok_or_else(|| {
+                Error::msg(format!(
+                    "Release {} does not include {}.",
+                    release.tag_name, name
+                ))
+            })
+    };
+    let archive = find(&archive_name)?;
+    let checksum = find(&checksum_name)?;
+
+    write_with_header(&mut output, "Downloading".green(), &archive.name);
+    let archive = get_bytes(&archive.browser_download_url).await?;
+    let checksum =
+        String::from_utf8(get_bytes(&checksum.browser_download_url).await?)?;
+
+    // checksum files are in the `sha256sum` format: "<hash>  <file name>".
+    let expected = checksum
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| Error::msg("Checksum file is empty."))?;
+    let actual = Sha256::digest(&archive)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+
+    if !expected.eq_ignore_ascii_case(&actual) {
+        return Err(Error::msg(format!(
+            "Checksum mismatch for {}: expected {}, got {}.",
+            archive_name, expected, actual
+        )));
+    }
+    write_with_header(&mut output, "Verified".green(), &actual);
+
+    let binary = extract_binary(&archive)?;
+
+    // a temporary file with a random name, created exclusively and only
+    // writable by us, so it cannot be planted or swapped by another user.
+    let mut file = tempfile::Builder::new()
+        .prefix("umi-")
+        .suffix(std::env::consts::EXE_SUFFIX)
+        .tempfile()?;
+    file.write_all(&binary)?;
+    file.flush()?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.as_file()
+            .set_permissions(std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    // check what was written through the same handle that is installed.
+    let mut written = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_end(&mut written)?;
+    if Sha256::digest(&written) != Sha256::digest(&binary) {
+        return Err(Error::msg(
+            "Extracted binary changed while being written, not installing.",
+        ));
+    }
+
+    write_with_header(&mut output, "Installing".green(), &release.tag_name);
+    self_replace::self_replace(file.path())?;
+
+    write_with_header(&mut output, "Done".green(), " ");
+
+    Ok(())
+}
+
+#[cfg(unix)]
+const ARCHIVE_EXTENSION: &str = ".tar.xz";
+
+#[cfg(windows)]
+const ARCHIVE_EXTENSION: &str = ".zip";
+
+/// Extract the `umi` binary from a release archive.
+#[cfg(unix)]
+fn extract_binary(archive: &[u8]) -> anyhow::Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut archive = tar::Archive::new(xz2::read::XzDecoder::new(archive));
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.file_name() == Some("umi".as_ref()) {
+            let mut binary = Vec::new();
+            entry.read_to_end(&mut binary)?;
+            return Ok(binary);
+        }
+    }
+
+    Err(Error::msg(
+        "Release archive does not contain the umi binary.",
+    ))
+}
+
+/// Extract the `umi` binary from a release archive.
+#[cfg(windows)]
+fn extract_binary(archive: &[u8]) -> anyhow::Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(archive))?;
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if file
+            .enclosed_name()
+            .and_then(|p| p.file_name().map(|n| n == "umi.exe"))
+            == Some(true)
+        {
+            let mut binary = Vec::new();
+            file.read_to_end(&mut binary)?;
+            return Ok(binary);
+        }
+    }
+
+    Err(Error::msg(
+        "Release archive does not contain the umi binary.",
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
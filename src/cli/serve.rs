@@ -0,0 +1,642 @@
+//! Local HTTP API.
+//!
+//! Exposes device management to local tools such as test dashboards. Gateway
+//! requests connect on demand, the same way the commands do.
+//!
+//! | Method | Path                          | Description                   |
+//! |--------|-------------------------------|-------------------------------|
+//! | GET    | `/adapters`                   | List attached Adapters        |
+//! | GET    | `/gateways`                   | List configured Gateways      |
+//! | GET    | `/gateways/{device}/status`   | Gateway status                |
+//! | GET    | `/gateways/{device}/config`   | Gateway configuration         |
+//! | PATCH  | `/gateways/{device}/config`   | Change Gateway configuration  |
+//! | POST   | `/gateways/{device}/restart`  | Restart a Gateway             |
+//! | POST   | `/gateways/{device}/reset`    | Reset a Gateway configuration |
+//! | POST   | `/gateways/{device}/update`   | Start a firmware update       |
+//! | GET    | `/updates/{id}/events`        | Update progress events (SSE)  |
+//!
+//! To keep web pages from driving the API, requests must name an allowed
+//! host and come from the same origin or an origin given with
+//! `--allow-origin`, and requests that change anything must have a JSON body.
+//! Allowed origins get CORS headers and preflight responses. Resets also need
+//! the serial number of the gateway.
+
+use crate::{
+    cli::config::Config,
+    cli::gateway::{
+        self, config, reset, restart,
+        update::{self, Firmware, Progress},
+        ConnectionOptions,
+    },
+    write_with_header,
+};
+use axum::{
+    extract::{Path, Request, State},
+    http::{header, uri::Authority, HeaderValue, Method, StatusCode},
+    middleware::{self, Next},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    Json, Router,
+};
+use clap::Parser;
+use colored::Colorize;
+use futures::{stream, Stream};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::{net::TcpListener, sync::watch};
+use tracing::debug;
+use umi::{
+    adapter::{self, format_bcd_version, DeviceState},
+    channel::Channel,
+    error::Error,
+    gateway::{client::DeviceIdentifier, status::Status},
+};
+
+/// How long the progress of a finished update can still be fetched.
+const FINISHED_UPDATE_RETENTION: Duration = Duration::from_secs(60);
+
+#[derive(Parser)]
+pub struct Cmd {
+    /// Address to serve the API on.
+    #[clap(long, default_value = "127.0.0.1:9188")]
+    listen: SocketAddr,
+    /// Host name clients reach the API by, in addition to localhost and the
+    /// listen address. Can be given more than once.
+    #[clap(long, value_name = "HOST")]
+    allow_host: Vec<String>,
+    /// Origin of a web page allowed to call the API, e.g.
+    /// "http://localhost:3000". Can be given more than once.
+    #[clap(long, value_name = "ORIGIN")]
+    allow_origin: Vec<String>,
+    #[clap(flatten)]
+    connection: ConnectionOptions,
+}
+
+impl Cmd {
+    pub async fn run(self) -> anyhow::Result<()> {
+        let mut output = std::io::stdout();
+
+        let listener = TcpListener::bind(self.listen).await.map_err(|err| {
+            Error::Validation(format!(
+                "Could not listen on {}: {}.",
+                self.listen, err
+            ))
+        })?;
+
+        write_with_header(
+            &mut output,
+            "Serving".green(),
+            &format!("API on http://{}", self.listen),
+        );
+
+        let mut hosts = vec![
+            "localhost".to_string(),
+            "127.0.0.1".to_string(),
+            "[::1]".to_string(),
+        ];
+        if !self.listen.ip().is_unspecified() {
+            hosts.push(match self.listen.ip() {
+                IpAddr::V4(ip) => ip.to_string(),
+                IpAddr::V6(ip) => format!("[{}]", ip),
+            });
+        }
+        hosts.extend(self.allow_host);
+
+        let server = Arc::new(Server {
+            connection: self.connection,
+            hosts,
+            origins: self.allow_origin,
+            updates: Mutex::new(HashMap::new()),
+            next_update: AtomicU64::new(1),
+        });
+
+        let app = Router::new()
+            .route("/adapters", get(adapters))
+            .route("/gateways", get(gateways))
+            .route("/gateways/{device}/status", get(gateway_status))
+            .route(
+                "/gateways/{device}/config",
+                get(gateway_config).patch(set_gateway_config),
+            )
+            .route("/gateways/{device}/restart", post(restart_gateway))
+            .route("/gateways/{device}/reset", post(reset_gateway))
+            .route("/gateways/{device}/update", post(start_update))
+            .route("/updates/{id}/events", get(update_events))
+            .layer(middleware::from_fn_with_state(server.clone(), guard))
+            .with_state(server);
+
+        axum::serve(listener, app).await?;
+
+        Ok(())
+    }
+}
+
+/// Shared server state.
+struct Server {
+    connection: ConnectionOptions,
+    /// Host names requests may be addressed to.
+    hosts: Vec<String>,
+    /// Cross-origin web pages allowed to call the API.
+    origins: Vec<String>,
+    /// Progress of firmware updates by ID.
+    updates: Mutex<HashMap<u64, watch::Receiver<Progress>>>,
+    next_update: AtomicU64,
+}
+
+impl Server {
+    fn target(&self, device: &str) -> anyhow::Result<gateway::Target> {
+        gateway::Target::resolve(
+            device,
+            &Config::load()?.gateway,
+            &self.connection,
+        )
+    }
+}
+
+/// Reject requests a web page could have made on behalf of a visitor.
+///
+/// A page can send simple cross-origin requests, or reach the API by DNS
+/// rebinding. Those have a foreign host or origin, or can't have a JSON body
+/// without a CORS preflight, which is only answered for allowed origins.
+async fn guard(
+    State(server): State<Arc<Server>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let headers = request.headers();
+    let header = |name| {
+        headers
+            .get(name)
+            .map(|value: &HeaderValue| value.to_str().unwrap_or_default())
+    };
+
+    let origin = header(header::ORIGIN);
+    if let Err(err) = check_origin(
+        header(header::HOST),
+        origin,
+        &server.hosts,
+        &server.origins,
+    ) {
+        return (StatusCode::FORBIDDEN, Json(json!({ "error": err })))
+            .into_response();
+    }
+
+    // same-origin requests don't need CORS headers.
+    let cors = origin
+        .filter(|origin| server.origins.iter().any(|o| o == origin))
+        .and_then(|origin| HeaderValue::from_str(origin).ok());
+
+    let mut response = if request.method() == Method::OPTIONS {
+        preflight()
+    } else if request.method() != Method::GET && !is_json(header) {
+        (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Json(json!({ "error": "Requests must have a JSON body." })),
+        )
+            .into_response()
+    } else {
+        next.run(request).await
+    };
+
+    if let Some(origin) = cors {
+        let headers = response.headers_mut();
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        headers.insert(header::VARY, HeaderValue::from_static("origin"));
+    }
+
+    response
+}
+
+/// Check the request has a JSON body.
+fn is_json<'a>(header: impl Fn(header::HeaderName) -> Option<&'a str>) -> bool {
+    header(header::CONTENT_TYPE).is_some_and(|content_type| {
+        content_type
+            .split(';')
+            .next()
+            .is_some_and(|mime| mime.trim() == "application/json")
+    })
+}
+
+/// Response to a CORS preflight request.
+fn preflight() -> Response {
+    (
+        StatusCode::NO_CONTENT,
+        [
+            (header::ACCESS_CONTROL_ALLOW_METHODS, "GET, POST, PATCH"),
+            (header::ACCESS_CONTROL_ALLOW_HEADERS, "content-type"),
+            (header::ACCESS_CONTROL_MAX_AGE, "600"),
+        ],
+    )
+        .into_response()
+}
+
+/// Check a request is addressed to an allowed host and comes from the same
+/// origin or an allowed origin, if any.
+fn check_origin(
+    host: Option<&str>,
+    origin: Option<&str>,
+    hosts: &[String],
+    origins: &[String],
+) -> Result<(), String> {
+    let host = host.ok_or("Requests must have a Host header.")?;
+    let authority: Authority = host
+        .parse()
+        .map_err(|_| format!("Invalid Host header {}.", host))?;
+    if !hosts
+        .iter()
+        .any(|h| h.eq_ignore_ascii_case(authority.host()))
+    {
+        return Err(format!(
+            "Host {} is not allowed, see --allow-host.",
+            authority.host()
+        ));
+    }
+
+    match origin {
+        Some(origin)
+            if origin != format!("http://{}", authority)
+                && !origins.iter().any(|o| o == origin) =>
+        {
+            Err(format!(
+                "Requests from {} are not allowed, see --allow-origin.",
+                origin
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+type ApiResult<T> = Result<T, ApiError>;
+
+/// Command error returned as a JSON response.
+struct ApiError(anyhow::Error);
+
+impl<E: Into<anyhow::Error>> From<E> for ApiError {
+    fn from(err: E) -> Self {
+        Self(err.into())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = status_code(&self.0);
+        debug!(%status, err = %self.0, "request failed");
+        (status, Json(json!({ "error": self.0.to_string() }))).into_response()
+    }
+}
+
+/// HTTP status for an error, following the exit code categories.
+fn status_code(err: &anyhow::Error) -> StatusCode {
+    match err.chain().find_map(|err| err.downcast_ref::<Error>()) {
+        Some(Error::Validation(_)) => StatusCode::BAD_REQUEST,
+        Some(Error::Unreachable(_) | Error::Exception(_)) => {
+            StatusCode::BAD_GATEWAY
+        }
+        Some(Error::Timeout(_)) => StatusCode::GATEWAY_TIMEOUT,
+        Some(Error::Unsupported(_)) => StatusCode::UNPROCESSABLE_ENTITY,
+        Some(Error::Firmware(_)) | None => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Serialize)]
+struct Adapter {
+    serial: Option<String>,
+    state: String,
+    product: Option<String>,
+    firmware_version: String,
+}
+
+async fn adapters() -> ApiResult<Json<Vec<Adapter>>> {
+    let adapters = adapter::list_devices()?
+        .iter()
+        .map(|dev| Adapter {
+            serial: dev.serial_number().map(String::from),
+            state: DeviceState::of(dev).to_string(),
+            product: dev.product_string().map(String::from),
+            firmware_version: format_bcd_version(dev.device_version()),
+        })
+        .collect();
+
+    Ok(Json(adapters))
+}
+
+async fn gateways() -> ApiResult<Json<Vec<String>>> {
+    Ok(Json(Config::load()?.gateway.devices.into_keys().collect()))
+}
+
+async fn gateway_status(
+    State(server): State<Arc<Server>>,
+    Path(device): Path<String>,
+) -> ApiResult<Json<Status>> {
+    let target = server.target(&device)?;
+    let mut client = target.connect().await?;
+    let status = Status::read(&mut client).await?;
+    target.remember(&status);
+
+    Ok(Json(status))
+}
+
+/// Gateway configuration.
+///
+/// Fields left out of a change are not modified.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GatewayConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dhcp: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ipv4_address: Option<Ipv4Addr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bitrate_nominal: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bitrate_data: Option<u32>,
+}
+
+async fn gateway_config(
+    State(server): State<Arc<Server>>,
+    Path(device): Path<String>,
+) -> ApiResult<Json<GatewayConfig>> {
+    let target = server.target(&device)?;
+    let mut client = target.connect().await?;
+
+    let mut config = GatewayConfig {
+        dhcp: Some(client.dhcp().await?),
+        ipv4_address: Some(client.ipv4_address().await?),
+        ..Default::default()
+    };
+
+    if matches!(client.device_identifier().await?, DeviceIdentifier::CanFd) {
+        config.bitrate_nominal = Some(client.canbus_bitrate_nominal().await?);
+        config.bitrate_data = Some(client.canbus_bitrate_data().await?);
+    }
+
+    Ok(Json(config))
+}
+
+async fn set_gateway_config(
+    State(server): State<Arc<Server>>,
+    Path(device): Path<String>,
+    Json(change): Json<GatewayConfig>,
+) -> ApiResult<StatusCode> {
+    let target = server.target(&device)?;
+    let mut client = target.connect().await?;
+
+    if let Some(enable) = change.dhcp {
+        client.set_dhcp(enable).await?;
+    }
+
+    if let Some(ip) = change.ipv4_address {
+        client.set_ipv4_address(ip).await?;
+    }
+
+    if let Some(nominal) = change.bitrate_nominal {
+        config::require_can(&mut client).await?;
+        // use same as nominal if not specified
+        let data = change.bitrate_data.unwrap_or(nominal);
+        config::set_bitrate(&mut client, nominal, data).await?;
+    } else if change.bitrate_data.is_some() {
+        return Err(Error::Validation(
+            "A data bitrate requires a nominal bitrate.".to_string(),
+        )
+        .into());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Body of requests without parameters, an empty JSON object.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Empty {}
+
+async fn restart_gateway(
+    State(server): State<Arc<Server>>,
+    Path(device): Path<String>,
+    Json(Empty {}): Json<Empty>,
+) -> ApiResult<StatusCode> {
+    let target = server.target(&device)?;
+    restart::command(
+        std::io::sink(),
+        restart::RestartOptions::default(),
+        &target,
+    )
+    .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ResetRequest {
+    /// Serial number of the gateway, confirming the reset.
+    serial: String,
+    /// Address the gateway comes back on after the reset.
+    address: Option<IpAddr>,
+    /// Do not wait for the gateway to come back.
+    #[serde(default)]
+    no_wait: bool,
+}
+
+async fn reset_gateway(
+    State(server): State<Arc<Server>>,
+    Path(device): Path<String>,
+    Json(request): Json<ResetRequest>,
+) -> ApiResult<StatusCode> {
+    let target = server.target(&device)?;
+    let options = reset::ResetOptions::confirmed(
+        request.serial,
+        request.address,
+        request.no_wait,
+    );
+    reset::command(std::io::sink(), options, &target).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UpdateRequest {
+    /// Update to a specific version.
+    version: Option<String>,
+    /// Release channel, defaults to the channel set in the config file.
+    channel: Option<Channel>,
+    /// Skip hardware compatibility checks.
+    #[serde(default)]
+    force: bool,
+}
+
+async fn start_update(
+    State(server): State<Arc<Server>>,
+    Path(device): Path<String>,
+    Json(request): Json<UpdateRequest>,
+) -> ApiResult<(StatusCode, Json<Value>)> {
+    if request.version.is_some() && request.channel.is_some() {
+        return Err(Error::Validation(
+            "Give either a version or a channel, not both.".to_string(),
+        )
+        .into());
+    }
+
+    let target = server.target(&device)?;
+    update::require_network(&target)?;
+
+    let id = server.next_update.fetch_add(1, Ordering::Relaxed);
+    let (progress, receiver) = watch::channel(Progress::Starting);
+    server
+        .updates
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .insert(id, receiver);
+
+    tokio::spawn(async move {
+        let result = update::install(
+            &target,
+            Firmware::Release {
+                version: request.version,
+                channel: request.channel,
+            },
+            request.force,
+            |update| {
+                progress.send_replace(update);
+            },
+        )
+        .await;
+
+        progress.send_replace(match result {
+            Ok(version) => Progress::Done { version },
+            Err(err) => Progress::Failed {
+                error: err.to_string(),
+            },
+        });
+
+        // give subscribers a chance to see how it ended.
+        tokio::time::sleep(FINISHED_UPDATE_RETENTION).await;
+        server
+            .updates
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .remove(&id);
+    });
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(json!({
+            "id": id,
+            "events": format!("/updates/{}/events", id),
+        })),
+    ))
+}
+
+async fn update_events(
+    State(server): State<Arc<Server>>,
+    Path(id): Path<u64>,
+) -> ApiResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let receiver = server
+        .updates
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .get(&id)
+        .cloned()
+        .ok_or_else(|| {
+            Error::Validation(format!("Update {} was not found.", id))
+        })?;
+
+    Ok(Sse::new(progress_events(receiver)).keep_alive(KeepAlive::default()))
+}
+
+/// Stream progress changes until the update finishes.
+///
+/// Starts with the current progress so late subscribers see where the update
+/// is at.
+fn progress_events(
+    receiver: watch::Receiver<Progress>,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    stream::unfold(
+        (receiver, true, false),
+        |(mut receiver, first, finished)| async move {
+            if finished || (!first && receiver.changed().await.is_err()) {
+                return None;
+            }
+
+            let progress = receiver.borrow_and_update().clone();
+            let finished = progress.finished();
+            let event = Event::default()
+                .event(if finished { "end" } else { "progress" })
+                .json_data(&progress)
+                .unwrap_or_default();
+
+            Some((Ok(event), (receiver, false, finished)))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[test]
+    fn error_status() {
+        let err = anyhow::Error::from(Error::Timeout("timed out".to_string()));
+        assert_eq!(status_code(&err), StatusCode::GATEWAY_TIMEOUT);
+
+        let err = anyhow::Error::from(Error::Validation("bad".to_string()))
+            .context("Could not resolve device.");
+        assert_eq!(status_code(&err), StatusCode::BAD_REQUEST);
+
+        assert_eq!(
+            status_code(&anyhow::Error::msg("other")),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[test]
+    fn origins() {
+        let hosts = ["localhost".to_string(), "127.0.0.1".to_string()];
+        let origins = ["http://localhost:3000".to_string()];
+        let check = |host, origin| check_origin(host, origin, &hosts, &origins);
+
+        assert!(check(Some("127.0.0.1:9188"), None).is_ok());
+        assert!(check(Some("localhost:9188"), Some("http://localhost:9188"))
+            .is_ok());
+        assert!(check(Some("localhost:9188"), Some("http://localhost:3000"))
+            .is_ok());
+        assert!(
+            check(Some("127.0.0.1:9188"), Some("https://example.com")).is_err()
+        );
+        // DNS rebinding keeps the attacker's host name.
+        assert!(check(Some("evil.example:9188"), None).is_err());
+        assert!(check(
+            Some("evil.example:9188"),
+            Some("http://localhost:3000")
+        )
+        .is_err());
+        assert!(check(None, None).is_err());
+    }
+
+    #[tokio::test]
+    async fn progress_until_finished() {
+        let (progress, receiver) = watch::channel(Progress::Starting);
+        let mut events = Box::pin(progress_events(receiver));
+        assert!(events.next().await.is_some());
+
+        progress.send_replace(Progress::Done {
+            version: "v0.2.0".to_string(),
+        });
+
+        // the stream ends after the final progress.
+        assert!(events.next().await.is_some());
+        assert!(events.next().await.is_none());
+    }
+}
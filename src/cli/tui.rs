@@ -0,0 +1,827 @@
+//! Interactive terminal dashboard.
+//!
+//! Lists gateways from the config file and inventory alongside attached
+//! Adapters. The selected gateway is polled in the background, and actions
+//! run as tasks that report back over a channel so the screen stays live.
+
+use crate::cli::{
+    adapter,
+    config::Config,
+    gateway::{
+        config::{parse_enable, require_can, set_bitrate},
+        restart,
+        status::poll,
+        update::{self, Firmware, Progress},
+        ConnectionOptions, Target,
+    },
+    inventory::{Inventory, Kind},
+    logging,
+};
+use clap::Parser;
+use crossterm::event::{
+    Event, EventStream, KeyCode, KeyEventKind, KeyModifiers,
+};
+use futures::StreamExt;
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Gauge, List, ListItem, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+use std::{
+    collections::HashMap, io::IsTerminal, net::Ipv4Addr, time::Duration,
+};
+use tokio::{
+    sync::mpsc::{self, UnboundedSender},
+    task::JoinHandle,
+    time::MissedTickBehavior,
+};
+use umi::{
+    adapter::{format_bcd_version, list_devices, DeviceState},
+    error::Error,
+    gateway::status::Status,
+};
+
+/// How often the Adapter list is refreshed.
+const ADAPTER_REFRESH: Duration = Duration::from_secs(1);
+
+#[derive(Parser)]
+pub struct Cmd {
+    /// Status refresh interval, e.g. "500ms" or "5s".
+    #[clap(long, default_value = "1s", value_parser = humantime::parse_duration)]
+    interval: Duration,
+    #[clap(flatten)]
+    connection: ConnectionOptions,
+}
+
+impl Cmd {
+    pub async fn run(self) -> anyhow::Result<()> {
+        if !std::io::stdout().is_terminal() {
+            return Err(Error::Validation(
+                "The dashboard requires a terminal.".to_string(),
+            )
+            .into());
+        }
+
+        let (gateways, skipped) = discover(&self.connection)?;
+
+        let mut app = App::new(gateways, self.interval);
+        if !skipped.is_empty() {
+            app.notice = Some(Err(skipped.join(" ")));
+        }
+
+        let mut terminal = ratatui::init();
+        // anything written to stderr would corrupt the screen.
+        logging::hide_stderr(true);
+        let result = app.run(&mut terminal).await;
+        logging::hide_stderr(false);
+        ratatui::restore();
+
+        result
+    }
+}
+
+/// Gateways from the config file, then any others in the inventory.
+///
+/// Devices that can't be resolved are skipped, and returned as messages.
+fn discover(
+    connection: &ConnectionOptions,
+) -> anyhow::Result<(Vec<Target>, Vec<String>)> {
+    let fleet = Config::load()?.gateway;
+
+    let mut gateways = Vec::new();
+    let mut skipped = Vec::new();
+    for name in fleet.devices.keys() {
+        match Target::resolve(name, &fleet, connection) {
+            Ok(target) => gateways.push(target),
+            Err(err) => skipped.push(format!("Skipped {}: {}", name, err)),
+        }
+    }
+
+    let known = gateways.iter().filter_map(Target::ip).collect::<Vec<_>>();
+
+    let inventory = Inventory::load().unwrap_or_else(|err| {
+        skipped.push(format!("Skipped the inventory: {}", err));
+        Inventory::default()
+    });
+
+    for record in inventory.devices {
+        let Some(address) = record.address else {
+            continue;
+        };
+
+        if record.kind != Kind::Gateway
+            || known.iter().any(|ip| ip.to_string() == address)
+        {
+            continue;
+        }
+
+        if let Ok(target) = Target::resolve(&address, &fleet, connection) {
+            gateways.push(target);
+        }
+    }
+
+    Ok((gateways, skipped))
+}
+
+/// Attached Adapter.
+#[derive(Debug, Clone)]
+struct Adapter {
+    serial: Option<String>,
+    product: Option<String>,
+    state: DeviceState,
+    firmware_version: String,
+}
+
+enum Selected<'a> {
+    Gateway(&'a Target),
+    Adapter(&'a Adapter),
+}
+
+impl Selected<'_> {
+    /// Key used to track status and updates of the device.
+    fn key(&self) -> String {
+        match self {
+            Self::Gateway(target) => format!("gateway:{}", target.name),
+            Self::Adapter(adapter) => format!(
+                "adapter:{}",
+                adapter.serial.as_deref().unwrap_or_default()
+            ),
+        }
+    }
+}
+
+/// What keys do.
+enum Mode {
+    Normal,
+    /// Waiting for confirmation of an action.
+    Confirm(Action),
+    /// Typing a config change.
+    Edit(String),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Action {
+    Restart,
+    Update,
+}
+
+/// Result of a background task.
+enum Message {
+    Status(String, Result<Status, String>),
+    Progress(String, Progress),
+    Finished(String, Result<String, String>),
+}
+
+/// Config change typed in the dashboard, same as `umi gateway config`.
+#[derive(Debug, PartialEq)]
+enum Edit {
+    Dhcp(bool),
+    Ipv4(Ipv4Addr),
+    CanBitrate(u32, Option<u32>),
+}
+
+fn parse_edit(input: &str) -> Result<Edit, String> {
+    let mut words = input.split_whitespace();
+
+    let edit = match (words.next(), words.next(), words.next()) {
+        (Some("dhcp"), Some(enable), None) => Edit::Dhcp(
+            parse_enable(enable)
+                .map_err(|_| format!("Invalid DHCP setting {}.", enable))?,
+        ),
+        (Some("ipv4"), Some(ip), None) => Edit::Ipv4(
+            ip.parse()
+                .map_err(|_| format!("Invalid IPv4 address {}.", ip))?,
+        ),
+        (Some("can-bitrate"), Some(nominal), data) => Edit::CanBitrate(
+            nominal
+                .parse()
+                .map_err(|_| format!("Invalid bitrate {}.", nominal))?,
+            data.map(|data| {
+                data.parse()
+                    .map_err(|_| format!("Invalid bitrate {}.", data))
+            })
+            .transpose()?,
+        ),
+        _ => return Err(
+            "Expected dhcp on|off, ipv4 ADDRESS or can-bitrate NOMINAL [DATA]."
+                .to_string(),
+        ),
+    };
+
+    match words.next() {
+        Some(_) => Err(format!("Too many values in {}.", input)),
+        None => Ok(edit),
+    }
+}
+
+struct App {
+    gateways: Vec<Target>,
+    adapters: Result<Vec<Adapter>, String>,
+    list: ListState,
+    /// Latest status of each polled gateway.
+    statuses: HashMap<String, Result<Status, String>>,
+    /// Progress of running updates.
+    updates: HashMap<String, Progress>,
+    /// Result of the last action.
+    notice: Option<Result<String, String>>,
+    mode: Mode,
+    interval: Duration,
+    /// Background poll of the selected gateway.
+    poller: Option<(String, JoinHandle<()>)>,
+    sender: UnboundedSender<Message>,
+    receiver: mpsc::UnboundedReceiver<Message>,
+    quit: bool,
+}
+
+impl App {
+    fn new(gateways: Vec<Target>, interval: Duration) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        let mut app = Self {
+            gateways,
+            adapters: Ok(Vec::new()),
+            list: ListState::default(),
+            statuses: HashMap::new(),
+            updates: HashMap::new(),
+            notice: None,
+            mode: Mode::Normal,
+            interval,
+            poller: None,
+            sender,
+            receiver,
+            quit: false,
+        };
+        app.refresh_adapters();
+        app.list.select_first();
+
+        app
+    }
+
+    async fn run(
+        mut self,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<()> {
+        let mut events = EventStream::new();
+        let mut ticker = tokio::time::interval(ADAPTER_REFRESH);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        while !self.quit {
+            self.follow_selection();
+            terminal.draw(|frame| self.draw(frame))?;
+
+            tokio::select! {
+                event = events.next() => match event {
+                    Some(event) => self.handle_event(event?),
+                    None => self.quit = true,
+                },
+                Some(message) = self.receiver.recv() => {
+                    self.handle_message(message)
+                }
+                _ = ticker.tick() => self.refresh_adapters(),
+            }
+        }
+
+        if let Some((_, poller)) = self.poller.take() {
+            poller.abort();
+        }
+
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.gateways.len() + self.adapters.as_ref().map_or(0, Vec::len)
+    }
+
+    fn selected(&self) -> Option<Selected<'_>> {
+        self.device(self.list.selected()?)
+    }
+
+    fn device(&self, index: usize) -> Option<Selected<'_>> {
+        match index.checked_sub(self.gateways.len()) {
+            None => self.gateways.get(index).map(Selected::Gateway),
+            Some(index) => self
+                .adapters
+                .as_ref()
+                .ok()?
+                .get(index)
+                .map(Selected::Adapter),
+        }
+    }
+
+    fn refresh_adapters(&mut self) {
+        let selected = self.selected().map(|s| s.key());
+
+        self.adapters = list_devices()
+            .map(|devices| {
+                devices
+                    .iter()
+                    .map(|dev| Adapter {
+                        serial: dev.serial_number().map(String::from),
+                        product: dev.product_string().map(String::from),
+                        state: DeviceState::of(dev),
+                        firmware_version: format_bcd_version(
+                            dev.device_version(),
+                        ),
+                    })
+                    .collect()
+            })
+            .map_err(|err| err.to_string());
+
+        // keep the same device selected as Adapters come and go.
+        let index = (0..self.len())
+            .find(|&i| self.device(i).map(|d| d.key()) == selected);
+
+        match index {
+            Some(index) => self.list.select(Some(index)),
+            None if self.len() == 0 => self.list.select(None),
+            None => self.list.select(Some(
+                self.list.selected().unwrap_or(0).min(self.len() - 1),
+            )),
+        }
+    }
+
+    /// Poll the selected gateway, and only that one.
+    fn follow_selection(&mut self) {
+        let target = match self.selected() {
+            Some(Selected::Gateway(target)) => Some(target.clone()),
+            _ => None,
+        };
+
+        let current = self.poller.as_ref().map(|(name, _)| name);
+        if current == target.as_ref().map(|t| &t.name) {
+            return;
+        }
+
+        if let Some((_, poller)) = self.poller.take() {
+            poller.abort();
+        }
+
+        if let Some(target) = target {
+            let name = target.name.clone();
+            let poller = tokio::spawn(poll_status(
+                target,
+                self.interval,
+                self.sender.clone(),
+            ));
+            self.poller = Some((name, poller));
+        }
+    }
+
+    fn handle_event(&mut self, event: Event) {
+        let Event::Key(key) = event else {
+            return;
+        };
+
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+
+        if key.modifiers.contains(KeyModifiers::CONTROL)
+            && key.code == KeyCode::Char('c')
+        {
+            self.quit = true;
+            return;
+        }
+
+        match &mut self.mode {
+            Mode::Normal => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+                KeyCode::Up | KeyCode::Char('k') => self.list.select_previous(),
+                KeyCode::Down | KeyCode::Char('j')
+                    if self.list.selected().map_or(0, |i| i + 1)
+                        < self.len() =>
+                {
+                    self.list.select_next()
+                }
+                KeyCode::Char('r') => self.confirm(Action::Restart),
+                KeyCode::Char('u') => self.confirm(Action::Update),
+                KeyCode::Char('e') => {
+                    if let Some(Selected::Gateway(_)) = self.selected() {
+                        self.mode = Mode::Edit(String::new());
+                    }
+                }
+                _ => {}
+            },
+            Mode::Confirm(action) => {
+                let action = *action;
+                self.mode = Mode::Normal;
+                if key.code == KeyCode::Char('y') {
+                    self.start(action);
+                }
+            }
+            Mode::Edit(input) => match key.code {
+                KeyCode::Esc => self.mode = Mode::Normal,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Enter => {
+                    let input = std::mem::take(input);
+                    self.mode = Mode::Normal;
+                    match parse_edit(&input) {
+                        Ok(edit) => self.edit(edit),
+                        Err(err) => self.notice = Some(Err(err)),
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+
+    fn confirm(&mut self, action: Action) {
+        let Some(selected) = self.selected() else {
+            return;
+        };
+
+        if self.updates.contains_key(&selected.key()) {
+            self.notice = Some(Err("An update is in progress.".to_string()));
+            return;
+        }
+
+        match (action, &selected) {
+            (Action::Restart, Selected::Adapter(_)) => {}
+            (Action::Update, Selected::Adapter(adapter))
+                if adapter.serial.is_none() => {}
+            _ => self.mode = Mode::Confirm(action),
+        }
+    }
+
+    /// Run a confirmed action in the background.
+    fn start(&mut self, action: Action) {
+        let Some(selected) = self.selected() else {
+            return;
+        };
+        let key = selected.key();
+        let sender = self.sender.clone();
+
+        match (action, selected) {
+            (Action::Restart, Selected::Gateway(target)) => {
+                let target = target.clone();
+                tokio::spawn(async move {
+                    let result = restart::command(
+                        std::io::sink(),
+                        restart::RestartOptions::default(),
+                        &target,
+                    )
+                    .await;
+                    let _ = sender.send(Message::Finished(
+                        key,
+                        result
+                            .map(|_| format!("Restarted {}.", target.name))
+                            .map_err(|err| err.to_string()),
+                    ));
+                });
+            }
+            (Action::Update, Selected::Gateway(target)) => {
+                let target = target.clone();
+                self.updates.insert(key.clone(), Progress::Starting);
+                tokio::spawn(async move {
+                    let firmware = Firmware::Release {
+                        version: None,
+                        channel: None,
+                    };
+                    let result =
+                        update::install(&target, firmware, false, |p| {
+                            let _ =
+                                sender.send(Message::Progress(key.clone(), p));
+                        })
+                        .await;
+                    let _ = sender.send(Message::Finished(
+                        key,
+                        result
+                            .map(|version| {
+                                format!(
+                                    "Updated {} to {}.",
+                                    target.name, version
+                                )
+                            })
+                            .map_err(|err| err.to_string()),
+                    ));
+                });
+            }
+            (Action::Update, Selected::Adapter(device)) => {
+                let Some(serial) = device.serial.clone() else {
+                    return;
+                };
+                self.updates.insert(key.clone(), Progress::Starting);
+                tokio::spawn(async move {
+                    let options =
+                        adapter::UpdateOptions::serial(serial.clone());
+                    let result = adapter::update::command(
+                        std::io::sink(),
+                        options,
+                        |block, total_blocks| {
+                            let _ = sender.send(Message::Progress(
+                                key.clone(),
+                                Progress::Loading {
+                                    block,
+                                    total_blocks,
+                                },
+                            ));
+                        },
+                    )
+                    .await;
+                    let _ = sender.send(Message::Finished(
+                        key,
+                        result
+                            .map(|_| format!("Updated {}.", serial))
+                            .map_err(|err| err.to_string()),
+                    ));
+                });
+            }
+            (Action::Restart, Selected::Adapter(_)) => {}
+        }
+    }
+
+    /// Apply a config change to the selected gateway in the background.
+    fn edit(&mut self, edit: Edit) {
+        let Some(Selected::Gateway(target)) = self.selected() else {
+            return;
+        };
+        let target = target.clone();
+        let key = format!("gateway:{}", target.name);
+        let sender = self.sender.clone();
+
+        tokio::spawn(async move {
+            let result = async {
+                let mut client = target.connect().await?;
+                match edit {
+                    Edit::Dhcp(enable) => client.set_dhcp(enable).await?,
+                    Edit::Ipv4(ip) => client.set_ipv4_address(ip).await?,
+                    Edit::CanBitrate(nominal, data) => {
+                        require_can(&mut client).await?;
+                        let data = data.unwrap_or(nominal);
+                        set_bitrate(&mut client, nominal, data).await?;
+                    }
+                }
+                anyhow::Ok(())
+            }
+            .await;
+
+            let _ = sender.send(Message::Finished(
+                key,
+                result
+                    .map(|_| format!("Changed config of {}.", target.name))
+                    .map_err(|err| err.to_string()),
+            ));
+        });
+    }
+
+    fn handle_message(&mut self, message: Message) {
+        match message {
+            Message::Status(name, status) => {
+                self.statuses.insert(name, status);
+            }
+            Message::Progress(key, progress) => {
+                self.updates.insert(key, progress);
+            }
+            Message::Finished(key, result) => {
+                self.updates.remove(&key);
+                self.notice = Some(result);
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, notice, footer] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let [list, details] =
+            Layout::horizontal([Constraint::Length(32), Constraint::Min(0)])
+                .areas(main);
+
+        self.draw_list(frame, list);
+        self.draw_details(frame, details);
+
+        match &self.notice {
+            Some(Ok(text)) => {
+                frame.render_widget(text.as_str().green(), notice)
+            }
+            Some(Err(text)) => frame.render_widget(text.as_str().red(), notice),
+            None => {}
+        }
+
+        let footer_text = match &self.mode {
+            Mode::Normal => Line::from(
+                "↑/↓ select  r restart  e edit config  u update  q quit",
+            )
+            .dim(),
+            Mode::Confirm(action) => {
+                let name = match self.selected() {
+                    Some(Selected::Gateway(target)) => target.name.clone(),
+                    Some(Selected::Adapter(adapter)) => {
+                        adapter.serial.clone().unwrap_or_default()
+                    }
+                    None => String::new(),
+                };
+                Line::from(format!(
+                    "{} {}? y/n",
+                    match action {
+                        Action::Restart => "Restart",
+                        Action::Update => "Update the firmware of",
+                    },
+                    name
+                ))
+                .yellow()
+            }
+            Mode::Edit(input) => Line::from(vec![
+                Span::raw("config> ").yellow(),
+                Span::raw(input.as_str()),
+                Span::raw("_").slow_blink(),
+                Span::raw(
+                    "  dhcp on|off, ipv4 ADDRESS, can-bitrate NOMINAL [DATA]",
+                )
+                .dim(),
+            ]),
+        };
+        frame.render_widget(footer_text, footer);
+    }
+
+    fn draw_list(&mut self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let mut items = self
+            .gateways
+            .iter()
+            .map(|target| {
+                let dot = match self.statuses.get(&target.name) {
+                    Some(Ok(_)) => Span::raw("● ").green(),
+                    Some(Err(_)) => Span::raw("● ").red(),
+                    None => Span::raw("○ ").dim(),
+                };
+                ListItem::new(Line::from(vec![
+                    dot,
+                    Span::raw("GW  ").dim(),
+                    Span::raw(target.name.as_str()),
+                ]))
+            })
+            .collect::<Vec<_>>();
+
+        if let Ok(adapters) = &self.adapters {
+            items.extend(adapters.iter().map(|adapter| {
+                ListItem::new(Line::from(vec![
+                    Span::raw("● ").green(),
+                    Span::raw("USB ").dim(),
+                    Span::raw(adapter.serial.as_deref().unwrap_or("-")),
+                ]))
+            }));
+        }
+
+        let mut block = Block::bordered().title(" Devices ");
+        if let Err(err) = &self.adapters {
+            block =
+                block.title_bottom(Line::from(format!(" USB: {} ", err)).dim());
+        }
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+
+        frame.render_stateful_widget(list, area, &mut self.list);
+    }
+
+    fn draw_details(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let Some(selected) = self.selected() else {
+            let text = Paragraph::new(
+                "No devices. Add gateways to the config file or attach an Adapter.",
+            )
+            .block(Block::bordered());
+            frame.render_widget(text, area);
+            return;
+        };
+
+        let progress = self.updates.get(&selected.key());
+        let [fields, gauge] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(if progress.is_some() { 3 } else { 0 }),
+        ])
+        .areas(area);
+
+        let (title, lines) = match &selected {
+            Selected::Gateway(target) => {
+                let lines = match self.statuses.get(&target.name) {
+                    Some(Ok(status)) => status
+                        .fields()
+                        .into_iter()
+                        .map(|(key, label, value)| {
+                            let value = match key {
+                                "rx_errors" | "tx_errors" if value != "0" => {
+                                    value.red().bold()
+                                }
+                                _ => value.into(),
+                            };
+                            field(label, value)
+                        })
+                        .collect(),
+                    Some(Err(err)) => {
+                        vec![Line::from(format!("offline: {}", err)).red()]
+                    }
+                    None => vec![Line::from("Connecting...").dim()],
+                };
+                (format!(" {} ({}) ", target.name, target.transport), lines)
+            }
+            Selected::Adapter(adapter) => (
+                format!(" {} ", adapter.serial.as_deref().unwrap_or("Adapter")),
+                vec![
+                    field(
+                        "Product",
+                        adapter.product.clone().unwrap_or_default().into(),
+                    ),
+                    field(
+                        "Firmware Version",
+                        adapter.firmware_version.clone().into(),
+                    ),
+                    field("State", adapter.state.to_string().into()),
+                ],
+            ),
+        };
+
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(title)),
+            fields,
+        );
+
+        if let Some(progress) = progress {
+            let (ratio, label) = match progress {
+                Progress::Loading {
+                    block,
+                    total_blocks,
+                } => (
+                    (*block + 1) as f64 / (*total_blocks).max(1) as f64,
+                    format!("Loading block {}/{}", block + 1, total_blocks),
+                ),
+                Progress::Downloading { version } => {
+                    (0.0, format!("Downloading {}", version))
+                }
+                _ => (0.0, "Updating...".to_string()),
+            };
+
+            let gauge_widget = Gauge::default()
+                .block(Block::bordered().title(" Update "))
+                .gauge_style(Style::new().fg(Color::Green))
+                .ratio(ratio.clamp(0.0, 1.0))
+                .label(label);
+            frame.render_widget(gauge_widget, gauge);
+        }
+    }
+}
+
+/// Headed line like `write_with_header`.
+fn field<'a>(label: &'a str, value: Span<'a>) -> Line<'a> {
+    Line::from(vec![Span::raw(format!("{: >16} ", label)).green(), value])
+}
+
+/// Poll a gateway until the task is aborted.
+async fn poll_status(
+    target: Target,
+    interval: Duration,
+    sender: UnboundedSender<Message>,
+) {
+    let mut client = None;
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+
+        let status = poll(&target, &mut client)
+            .await
+            .map_err(|err| err.to_string());
+
+        if sender
+            .send(Message::Status(target.name.clone(), status))
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits() {
+        assert_eq!(parse_edit("dhcp off"), Ok(Edit::Dhcp(false)));
+        assert_eq!(
+            parse_edit("ipv4 10.0.0.5"),
+            Ok(Edit::Ipv4(Ipv4Addr::new(10, 0, 0, 5)))
+        );
+        assert_eq!(
+            parse_edit("can-bitrate 500000"),
+            Ok(Edit::CanBitrate(500_000, None))
+        );
+        assert_eq!(
+            parse_edit("can-bitrate 500000 2000000"),
+            Ok(Edit::CanBitrate(500_000, Some(2_000_000)))
+        );
+        assert!(parse_edit("dhcp maybe").is_err());
+        assert!(parse_edit("ipv4 10.0.0.5 extra").is_err());
+        assert!(parse_edit("").is_err());
+    }
+}
@@ -0,0 +1,87 @@
+//! Error categories.
+//!
+//! Each category exits the process with its own code so scripts can react
+//! without parsing the output.
+//!
+//! | Code | Category                                  |
+//! |------|-------------------------------------------|
+//! | 0    | Success                                   |
+//! | 1    | Other error                               |
+//! | 2    | Invalid input or command line arguments   |
+//! | 3    | Device unreachable or not found           |
+//! | 4    | Device did not respond in time            |
+//! | 5    | Device responded with a Modbus exception  |
+//! | 6    | Operation not supported by the device     |
+//! | 7    | Firmware could not be found or flashed    |
+
+use std::fmt::Display;
+use tokio_modbus::Exception;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Invalid input such as an unknown device name or out of range value.
+    Validation(String),
+    /// Device could not be reached or was not found.
+    Unreachable(String),
+    /// Device did not respond in time.
+    Timeout(String),
+    /// Device responded with a Modbus exception.
+    Exception(Exception),
+    /// Operation is not supported by the device.
+    Unsupported(String),
+    /// Firmware could not be found, downloaded or flashed.
+    Firmware(String),
+}
+
+impl Error {
+    /// Process exit code of the error category.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::Validation(_) => 2,
+            Self::Unreachable(_) => 3,
+            Self::Timeout(_) => 4,
+            Self::Exception(_) => 5,
+            Self::Unsupported(_) => 6,
+            Self::Firmware(_) => 7,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Validation(msg)
+            | Self::Unreachable(msg)
+            | Self::Timeout(msg)
+            | Self::Unsupported(msg)
+            | Self::Firmware(msg) => write!(f, "{}", msg),
+            Self::Exception(exception) => write!(
+                f,
+                "Device responded with Modbus exception {:#04x}: {}.",
+                u8::from(*exception),
+                exception
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes() {
+        let err = Error::Timeout("timed out".to_string());
+        assert_eq!(err.exit_code(), 4);
+
+        let err = Error::Exception(Exception::IllegalDataAddress);
+        assert_eq!(
+            err.to_string(),
+            "Device responded with Modbus exception 0x02: Illegal data address."
+        );
+    }
+}
@@ -1,150 +1,422 @@
-#![allow(unused)]
+//! Modbus client.
 
+use crate::error::{Error, Result};
 use std::{
+    borrow::Cow,
     fmt::Display,
-    net::{IpAddr, Ipv4Addr, SocketAddr},
+    future::Future,
+    io::ErrorKind,
+    net::{Ipv4Addr, SocketAddr},
+    pin::Pin,
+    time::Duration,
 };
+use tokio::time::timeout;
 use tokio_modbus::{
-    client::{tcp::connect, Reader, Writer},
+    client::{rtu, tcp, Client as _, Context, Reader, Writer},
     slave::SlaveContext,
-    Result as ModbusResult, Slave,
+    Request, Result as ModbusResult, Slave,
 };
+use tokio_serial::SerialPortBuilderExt;
+use tracing::{debug, instrument};
+
+/// Pending request as returned by `tokio_modbus`.
+type Pending<'a, T> =
+    Pin<Box<dyn Future<Output = ModbusResult<T>> + Send + 'a>>;
+
+/// Connection options.
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    /// Timeout for connecting and for each request.
+    pub timeout: Duration,
+    /// Number of times to retry a request that failed in transport.
+    pub retries: u32,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(3),
+            retries: 2,
+        }
+    }
+}
+
+/// Default baud rate for serial ports.
+const DEFAULT_BAUD_RATE: u32 = 115200;
+
+/// How the gateway is connected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transport {
+    /// Modbus TCP.
+    Tcp(SocketAddr),
+    /// Modbus RTU on a serial port.
+    Rtu { path: String, baud_rate: u32 },
+}
+
+impl Transport {
+    /// Parse a serial port given as `/dev/ttyUSB0@115200`.
+    ///
+    /// The baud rate is optional and defaults to 115200.
+    pub fn serial(port: &str) -> Result<Self> {
+        let (path, baud_rate) = match port.rsplit_once('@') {
+            Some((path, baud_rate)) => {
+                let baud_rate = baud_rate.parse().map_err(|_| {
+                    Error::Validation(format!(
+                        "{} is not a valid baud rate.",
+                        baud_rate
+                    ))
+                })?;
+                (path, baud_rate)
+            }
+            None => (port, DEFAULT_BAUD_RATE),
+        };
+
+        if path.is_empty() {
+            return Err(Error::Validation(
+                "Serial port path is empty.".to_string(),
+            ));
+        }
+
+        Ok(Self::Rtu {
+            path: path.to_string(),
+            baud_rate,
+        })
+    }
+
+    /// Open a Modbus context addressing the given unit.
+    async fn open(
+        &self,
+        unit_id: u8,
+        duration: Duration,
+    ) -> std::io::Result<Context> {
+        match self {
+            Self::Tcp(addr) => {
+                let mut modbus = timeout(duration, tcp::connect(*addr))
+                    .await
+                    .map_err(|_| timed_out(self))??;
+                modbus.set_slave(Slave(unit_id));
+                Ok(modbus)
+            }
+            Self::Rtu { path, baud_rate } => {
+                let port = tokio_serial::new(path, *baud_rate)
+                    .timeout(duration)
+                    .open_native_async()?;
+                Ok(rtu::attach_slave(port, Slave(unit_id)))
+            }
+        }
+    }
+}
+
+impl Display for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "{}", addr),
+            Self::Rtu { path, baud_rate } => {
+                write!(f, "serial:{}@{}", path, baud_rate)
+            }
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Client {
-    modbus: tokio_modbus::client::Context,
+    modbus: Context,
+    transport: Transport,
+    unit_id: u8,
+    options: Options,
 }
 
 impl Client {
-    pub async fn connect(ip: IpAddr) -> std::io::Result<Self> {
-        let mut modbus = connect(SocketAddr::new(ip, 502)).await?;
-        modbus.set_slave(Slave(1));
+    #[instrument(level = "debug", skip_all, fields(%transport, unit_id = unit_id))]
+    pub async fn connect(
+        transport: Transport,
+        unit_id: u8,
+        options: Options,
+    ) -> Result<Self> {
+        let mut attempt = 0;
 
-        Ok(Self { modbus })
+        let modbus = loop {
+            let result = transport.open(unit_id, options.timeout).await;
+            if let Err(err) = &result {
+                debug!(%err, attempt, "connect failed");
+            }
+
+            match result {
+                Ok(modbus) => break modbus,
+                Err(_) if attempt < options.retries => attempt += 1,
+                Err(err) if err.kind() == ErrorKind::TimedOut => {
+                    return Err(Error::Timeout(format!(
+                        "Timed out connecting to {}.",
+                        transport
+                    )))
+                }
+                Err(err) => {
+                    return Err(Error::Unreachable(format!(
+                        "Could not connect to {}: {}.",
+                        transport, err
+                    )))
+                }
+            }
+        };
+
+        Ok(Self {
+            modbus,
+            transport,
+            unit_id,
+            options,
+        })
     }
 
-    /// Get device identifier.
-    pub async fn device_identifier(
+    /// Run a request with the timeout applied.
+    ///
+    /// Requests that are safe to repeat are retried on transport errors.
+    async fn request<T: std::fmt::Debug>(
         &mut self,
-    ) -> ModbusResult<DeviceIdentifier> {
-        self.modbus
-            .read_holding_registers(0, 1)
-            .await
-            .map(|v| v.map(|v| v[0]).map(DeviceIdentifier::from))
+        idempotent: bool,
+        request: impl Fn(&mut Context) -> Pending<'_, T>,
+    ) -> Result<T> {
+        let mut attempt = 0;
+
+        loop {
+            let result =
+                timeout(self.options.timeout, request(&mut self.modbus))
+                    .await
+                    .unwrap_or_else(|_| Err(timed_out(&self.transport).into()));
+            debug!(?result, attempt, "response");
+
+            match result {
+                Err(tokio_modbus::Error::Transport(_))
+                    if idempotent && attempt < self.options.retries =>
+                {
+                    attempt += 1;
+                    debug!(attempt, "retrying");
+                    // a late response could still arrive on the old TCP
+                    // connection, so start afresh. Serial ports are opened
+                    // exclusively and cannot be reopened while in use.
+                    if let Transport::Tcp(_) = self.transport {
+                        if let Ok(modbus) = self
+                            .transport
+                            .open(self.unit_id, self.options.timeout)
+                            .await
+                        {
+                            self.modbus = modbus;
+                        }
+                    }
+                }
+                Ok(Ok(value)) => return Ok(value),
+                Ok(Err(exception)) => return Err(Error::Exception(exception)),
+                Err(tokio_modbus::Error::Transport(err))
+                    if err.kind() == ErrorKind::TimedOut =>
+                {
+                    return Err(Error::Timeout(err.to_string()))
+                }
+                Err(err) => {
+                    return Err(Error::Unreachable(format!(
+                        "Lost connection to {}: {}.",
+                        self.transport, err
+                    )))
+                }
+            }
+        }
+    }
+
+    /// Read holding registers, checking the response length.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn read_holding(
+        &mut self,
+        addr: u16,
+        count: u16,
+    ) -> Result<Vec<u16>> {
+        let words = self
+            .request(true, |m| m.read_holding_registers(addr, count))
+            .await?;
+        check_len(words, count)
+    }
+
+    /// Read input registers, checking the response length.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn read_input(
+        &mut self,
+        addr: u16,
+        count: u16,
+    ) -> Result<Vec<u16>> {
+        let words = self
+            .request(true, |m| m.read_input_registers(addr, count))
+            .await?;
+        check_len(words, count)
+    }
+
+    /// Read coils, checking the response length.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn read_coils(
+        &mut self,
+        addr: u16,
+        count: u16,
+    ) -> Result<Vec<bool>> {
+        let coils = self.request(true, |m| m.read_coils(addr, count)).await?;
+        check_len(coils, count)
+    }
+
+    /// Get device identifier.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn device_identifier(&mut self) -> Result<DeviceIdentifier> {
+        let id = self.read_holding(0, 1).await?;
+        Ok(DeviceIdentifier::from(id[0]))
     }
 
     /// Restart the gateway gracefully
-    pub async fn restart(&mut self) -> ModbusResult<()> {
-        self.modbus.write_single_coil(1, true).await
+    #[instrument(level = "debug", skip(self))]
+    pub async fn restart(&mut self) -> Result<()> {
+        self.request(false, |m| m.write_single_coil(1, true)).await
     }
 
     /// Reset the gateway to factory defaults
-    pub async fn reset(&mut self) -> ModbusResult<()> {
-        self.modbus.write_single_coil(2, true).await
+    #[instrument(level = "debug", skip(self))]
+    pub async fn reset(&mut self) -> Result<()> {
+        self.request(false, |m| m.write_single_coil(2, true)).await
     }
 
     /// Get hardware version.
-    pub async fn hardware_version(&mut self) -> ModbusResult<Version> {
-        let version = self.modbus.read_holding_registers(1, 3).await?.unwrap();
-        Ok(Ok(Version {
+    #[instrument(level = "debug", skip(self))]
+    pub async fn hardware_version(&mut self) -> Result<Version> {
+        let version = self.read_holding(1, 3).await?;
+        Ok(Version {
             major: version[0],
             minor: version[1],
             patch: version[2],
-        }))
+        })
     }
 
     /// Get firmware version.
-    pub async fn firmware_version(&mut self) -> ModbusResult<Version> {
-        let version = self.modbus.read_holding_registers(4, 3).await?.unwrap();
-        Ok(Ok(Version {
+    #[instrument(level = "debug", skip(self))]
+    pub async fn firmware_version(&mut self) -> Result<Version> {
+        let version = self.read_holding(4, 3).await?;
+        Ok(Version {
             major: version[0],
             minor: version[1],
             patch: version[2],
-        }))
+        })
     }
 
     /// Get serial number.
-    pub async fn serial(&mut self) -> ModbusResult<Serial> {
-        let serial = self.modbus.read_holding_registers(7, 2).await?.unwrap();
+    #[instrument(level = "debug", skip(self))]
+    pub async fn serial(&mut self) -> Result<Serial> {
+        let serial = self.read_holding(7, 2).await?;
 
-        Ok(Ok(Serial {
+        Ok(Serial {
             year: serial[0].to_le_bytes()[1],
             week: serial[0].to_le_bytes()[0],
             seq: serial[1],
-        }))
+        })
     }
 
     /// Get DHCP enabled.
-    pub async fn dhcp(&mut self) -> ModbusResult<bool> {
-        let enabled = self.modbus.read_coils(1001, 1).await?.unwrap();
-        Ok(Ok(enabled[0]))
+    #[instrument(level = "debug", skip(self))]
+    pub async fn dhcp(&mut self) -> Result<bool> {
+        let enabled = self.request(true, |m| m.read_coils(1001, 1)).await?;
+        enabled.first().copied().ok_or_else(short_response)
     }
 
     /// Set DHCP enabled.
-    pub async fn set_dhcp(&mut self, enabled: bool) -> ModbusResult<()> {
-        self.modbus.write_single_coil(1001, enabled).await
+    #[instrument(level = "debug", skip(self))]
+    pub async fn set_dhcp(&mut self, enabled: bool) -> Result<()> {
+        self.request(true, |m| m.write_single_coil(1001, enabled))
+            .await
     }
 
     /// Get the configured IPv4 address.
-    pub async fn ipv4_address(&mut self) -> ModbusResult<Ipv4Addr> {
-        let address = self.modbus.read_input_registers(1001, 4).await?.unwrap();
-        Ok(Ok(Ipv4Addr::new(
+    #[instrument(level = "debug", skip(self))]
+    pub async fn ipv4_address(&mut self) -> Result<Ipv4Addr> {
+        let address = self.read_input(1001, 4).await?;
+        Ok(Ipv4Addr::new(
             address[0] as u8,
             address[1] as u8,
             address[2] as u8,
             address[3] as u8,
-        )))
+        ))
     }
 
     /// Set the IPv4 address.
-    pub async fn set_ipv4_address(&mut self, ip: Ipv4Addr) -> ModbusResult<()> {
+    #[instrument(level = "debug", skip(self))]
+    pub async fn set_ipv4_address(&mut self, ip: Ipv4Addr) -> Result<()> {
         let words = ip.octets().map(|o| o as u16);
-        self.modbus.write_multiple_registers(1001, &words).await
+        self.request(true, |m| {
+            m.call(Request::WriteMultipleRegisters(
+                1001,
+                Cow::Owned(words.to_vec()),
+            ))
+        })
+        .await
+        .map(|_| ())
     }
 
     /// Get CAN bus receive error count.
-    pub async fn canbus_receive_error_count(&mut self) -> ModbusResult<u16> {
-        let count = self.modbus.read_input_registers(2001, 1).await?.unwrap();
-        Ok(Ok(count[0]))
+    #[instrument(level = "debug", skip(self))]
+    pub async fn canbus_receive_error_count(&mut self) -> Result<u16> {
+        let count = self.read_input(2001, 1).await?;
+        Ok(count[0])
     }
 
     /// Get CAN bus transmit error count.
-    pub async fn canbus_transmit_error_count(&mut self) -> ModbusResult<u16> {
-        let count = self.modbus.read_input_registers(2002, 1).await?.unwrap();
-        Ok(Ok(count[0]))
+    #[instrument(level = "debug", skip(self))]
+    pub async fn canbus_transmit_error_count(&mut self) -> Result<u16> {
+        let count = self.read_input(2002, 1).await?;
+        Ok(count[0])
     }
 
     /// Get the CAN bus nominal rate in bits per second.
-    pub async fn canbus_bitrate_nominal(&mut self) -> ModbusResult<u32> {
-        let rate = self.modbus.read_holding_registers(2001, 1).await?.unwrap();
-        Ok(Ok(rate[0] as u32 * 100))
+    #[instrument(level = "debug", skip(self))]
+    pub async fn canbus_bitrate_nominal(&mut self) -> Result<u32> {
+        let rate = self.read_holding(2001, 1).await?;
+        Ok(rate[0] as u32 * 100)
     }
 
     /// Set the CAN bus nominal rate in bits per second.
+    #[instrument(level = "debug", skip(self))]
     pub async fn set_canbus_bitrate_nominal(
         &mut self,
         rate: u32,
-    ) -> ModbusResult<()> {
+    ) -> Result<()> {
         let rate = (rate / 100) as u16;
-        self.modbus.write_single_register(2001, rate).await
+        self.request(true, |m| m.write_single_register(2001, rate))
+            .await
     }
 
     /// Get the CAN bus data rate in bits per second.
-    pub async fn canbus_bitrate_data(&mut self) -> ModbusResult<u32> {
-        let rate = self.modbus.read_holding_registers(2001, 1).await?.unwrap();
-        Ok(Ok(rate[0] as u32 * 100))
+    #[instrument(level = "debug", skip(self))]
+    pub async fn canbus_bitrate_data(&mut self) -> Result<u32> {
+        let rate = self.read_holding(2001, 1).await?;
+        Ok(rate[0] as u32 * 100)
     }
 
     /// Set the CAN bus data rate in bits per second.
-    pub async fn set_canbus_bitrate_data(
-        &mut self,
-        rate: u32,
-    ) -> ModbusResult<()> {
+    #[instrument(level = "debug", skip(self))]
+    pub async fn set_canbus_bitrate_data(&mut self, rate: u32) -> Result<()> {
         let rate = (rate / 100) as u16;
-        self.modbus.write_single_register(2001, rate).await
+        self.request(true, |m| m.write_single_register(2001, rate))
+            .await
     }
 }
 
+fn timed_out(transport: &Transport) -> std::io::Error {
+    std::io::Error::new(
+        ErrorKind::TimedOut,
+        format!("Timed out communicating with {}.", transport),
+    )
+}
+
+fn short_response() -> Error {
+    Error::Unreachable("Device returned a short response.".to_string())
+}
+
+/// Check a read returned as many values as requested.
+fn check_len<T>(values: Vec<T>, count: u16) -> Result<Vec<T>> {
+    if values.len() < count as usize {
+        return Err(short_response());
+    }
+    Ok(values)
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Serial {
     pub year: u8,
@@ -183,6 +455,27 @@ pub enum DeviceIdentifier {
     Unknown(u16),
 }
 
+impl DeviceIdentifier {
+    /// Human readable name of the device type.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::CanFd => "CAN FD",
+            Self::Serial => "RS-232/RS-485",
+            Self::Unknown(_) => "Unknown",
+        }
+    }
+
+    /// Two character code of the identifier, e.g. "FD".
+    pub fn code(&self) -> String {
+        let value = match self {
+            Self::CanFd => 0x4644,
+            Self::Serial => 0x5253,
+            Self::Unknown(value) => *value,
+        };
+        String::from_utf8_lossy(&u16::to_be_bytes(value)).into_owned()
+    }
+}
+
 impl From<u16> for DeviceIdentifier {
     fn from(value: u16) -> Self {
         match value {
@@ -192,3 +485,83 @@ impl From<u16> for DeviceIdentifier {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serial_transport() {
+        assert_eq!(
+            Transport::serial("/dev/ttyUSB0@9600").unwrap(),
+            Transport::Rtu {
+                path: "/dev/ttyUSB0".to_string(),
+                baud_rate: 9600,
+            }
+        );
+        assert_eq!(
+            Transport::serial("COM3").unwrap(),
+            Transport::Rtu {
+                path: "COM3".to_string(),
+                baud_rate: DEFAULT_BAUD_RATE,
+            }
+        );
+        assert!(Transport::serial("/dev/ttyUSB0@fast").is_err());
+        assert!(Transport::serial("@9600").is_err());
+    }
+
+    /// Modbus RTU frame checksum.
+    fn crc(frame: &[u8]) -> [u8; 2] {
+        let mut crc = 0xFFFF_u16;
+        for byte in frame {
+            crc ^= *byte as u16;
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0xA001
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+        crc.to_le_bytes()
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn rtu_over_pty() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio_serial::{SerialPort, SerialStream};
+
+        // the client opens the pty by path, keep this end open until done.
+        let (mut gateway, port) = SerialStream::pair().unwrap();
+        let path = port.name().unwrap();
+
+        let device = tokio::spawn(async move {
+            let mut request = [0; 8];
+            gateway.read_exact(&mut request).await.unwrap();
+            assert_eq!(request[..6], [5, 0x03, 0, 0, 0, 1]);
+            assert_eq!(request[6..], crc(&request[..6]));
+
+            let mut response = vec![5, 0x03, 2, 0x46, 0x44];
+            response.extend(crc(&response));
+            gateway.write_all(&response).await.unwrap();
+
+            // closing the pty before the client has read the response
+            // would hang up the line.
+            gateway
+        });
+
+        let transport = Transport::Rtu {
+            path,
+            baud_rate: DEFAULT_BAUD_RATE,
+        };
+        let mut client = Client::connect(transport, 5, Options::default())
+            .await
+            .unwrap();
+        let device_identifier = client.device_identifier().await.unwrap();
+
+        assert!(matches!(device_identifier, DeviceIdentifier::CanFd));
+        device.await.unwrap();
+        drop(port);
+    }
+}
@@ -1,15 +1,39 @@
+//! Firmware manifest.
+
+use super::client::{DeviceIdentifier, Version};
+use crate::{
+    channel::Channel,
+    error::{Error, Result},
+    http,
+};
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::{cmp::Ordering, collections::HashMap};
+
+/// Manifest location.
+const MANIFEST_URL: &str =
+    "https://cdn.umi.engineering/firmware/gateway/manifest.json";
 
 /// Manifest schema.
 ///
 /// Only deserializes the `schema` field.
 #[derive(Debug, Deserialize)]
-#[allow(unused)]
 pub struct ManifestSchema {
     pub schema: String,
 }
 
+impl ManifestSchema {
+    /// Major and minor parts of the schema version.
+    ///
+    /// Patch releases of the schema only add fields, so any patch release of a
+    /// supported schema can be parsed.
+    fn major_minor(&self) -> Option<(u32, u32)> {
+        let mut parts = self.schema.strip_prefix('v')?.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next()?.parse().ok()?;
+        Some((major, minor))
+    }
+}
+
 /// Manifest file format.
 ///
 /// # Example
@@ -19,8 +43,16 @@ pub struct ManifestSchema {
 ///     "schema": "v0.1.0",
 ///     "latest": "v0.3.0",
 ///     "stable": "v0.2.0",
+///     "channels": {
+///         "beta": "v0.3.0"
+///     },
 ///     "binaries": {
-///         "v0.2.0": { ... },
+///         "v0.2.0": {
+///             "file": "https://...",
+///             "min": "v0.1.0",
+///             "hardware": ["v1.0.0"],
+///             "devices": ["FD"]
+///         },
 ///         "v0.3.0": { ... },
 ///     }
 /// }
@@ -34,10 +66,112 @@ pub struct Manifest {
     pub latest: String,
     /// Stable firmware version.
     pub stable: String,
+    /// Firmware versions of named channels.
+    #[serde(default)]
+    pub channels: HashMap<String, String>,
     /// List of available binaries.
     pub binaries: HashMap<String, FirmwareBinary>,
 }
 
+impl Manifest {
+    /// Fetch the manifest from the CDN.
+    pub async fn fetch() -> Result<Self> {
+        let body = http::get_bytes(MANIFEST_URL).await.map_err(|err| {
+            Error::Firmware(format!(
+                "Could not fetch the manifest: {}.",
+                http::describe(&err)
+            ))
+        })?;
+
+        Self::from_slice(&body)
+    }
+
+    /// Parse a manifest, dispatching on its schema version.
+    pub fn from_slice(body: &[u8]) -> Result<Self> {
+        let schema =
+            serde_json::from_slice::<ManifestSchema>(body).map_err(|err| {
+                Error::Firmware(format!("Invalid manifest: {}", err))
+            })?;
+
+        match schema.major_minor() {
+            Some((0, 1)) => Self::from_slice_v0_1(body),
+            _ => Err(Error::Firmware(format!(
+                "Manifest schema {} is not supported by this version of the CLI (v{}). \
+                 Run `umi self update` to upgrade the CLI.",
+                schema.schema,
+                env!("CARGO_PKG_VERSION"),
+            ))),
+        }
+    }
+
+    /// Parse a v0.1.x schema manifest.
+    fn from_slice_v0_1(body: &[u8]) -> Result<Self> {
+        serde_json::from_slice::<Manifest>(body).map_err(|err| {
+            Error::Firmware(format!("Invalid v0.1 manifest: {}", err))
+        })
+    }
+
+    /// Firmware version of a release channel.
+    pub fn channel_version(&self, channel: &Channel) -> Result<&str> {
+        match channel {
+            Channel::Stable => Ok(&self.stable),
+            Channel::Latest => Ok(&self.latest),
+            Channel::Named(name) => {
+                self.channels.get(name).map(String::as_str).ok_or_else(|| {
+                    Error::Firmware(format!("Channel {} was not found.", name))
+                })
+            }
+        }
+    }
+
+    /// Version of a release channel that is newer than the current version.
+    pub fn update_available(
+        &self,
+        channel: &Channel,
+        current: &Version,
+    ) -> Result<Option<&str>> {
+        let version = self.channel_version(channel)?;
+
+        if compare_versions(version, &current.to_string()) == Ordering::Greater
+        {
+            Ok(Some(version))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Binaries sorted by version, oldest first.
+    pub fn sorted_binaries(&self) -> Vec<(&String, &FirmwareBinary)> {
+        let mut binaries = self.binaries.iter().collect::<Vec<_>>();
+        binaries.sort_by(|a, b| compare_versions(a.0, b.0));
+        binaries
+    }
+
+    /// Names of the channels pointing at a firmware version.
+    pub fn channels_of(&self, version: &str) -> Vec<String> {
+        let mut channels = Vec::new();
+
+        if self.stable == version {
+            channels.push(Channel::Stable.to_string());
+        }
+
+        if self.latest == version {
+            channels.push(Channel::Latest.to_string());
+        }
+
+        let mut named = self
+            .channels
+            .iter()
+            .filter(|(_, v)| *v == version)
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        named.sort();
+        channels.extend(named);
+
+        channels
+    }
+}
+
 /// Metadata for a firmware release binary.
 ///
 /// Each firmware binary _must_ specify the minimum required firmware version
@@ -50,4 +184,168 @@ pub struct FirmwareBinary {
     pub file: String,
     /// Minimum supported version.
     pub min: String,
+    /// Supported hardware versions. Any hardware version if empty.
+    #[serde(default)]
+    pub hardware: Vec<String>,
+    /// Supported device type codes such as "FD". Any device if empty.
+    #[serde(default)]
+    pub devices: Vec<String>,
+    /// Release notes.
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+impl FirmwareBinary {
+    /// Download the binary.
+    pub async fn download(&self) -> Result<Vec<u8>> {
+        http::get_bytes(&self.file).await.map_err(|err| {
+            Error::Firmware(format!(
+                "Could not download firmware: {}.",
+                http::describe(&err)
+            ))
+        })
+    }
+
+    /// Check the binary supports the device type.
+    pub fn supports_device(&self, device: DeviceIdentifier) -> bool {
+        self.devices.is_empty() || self.devices.contains(&device.code())
+    }
+
+    /// Check the binary supports the hardware version.
+    pub fn supports_hardware(&self, hardware: &Version) -> bool {
+        self.hardware.is_empty()
+            || self.hardware.contains(&hardware.to_string())
+    }
+
+    /// Check the binary is compatible with the device.
+    pub fn check_compatible(
+        &self,
+        device: DeviceIdentifier,
+        hardware: &Version,
+    ) -> Result<()> {
+        if !self.supports_device(device) {
+            return Err(Error::Unsupported(format!(
+                "Firmware does not support {} devices (supports {}).",
+                device.code(),
+                self.devices.join(", "),
+            )));
+        }
+
+        if !self.supports_hardware(hardware) {
+            return Err(Error::Unsupported(format!(
+                "Firmware does not support hardware version {} (supports {}).",
+                hardware,
+                self.hardware.join(", "),
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Compare version strings such as "v0.2.0" by semantic version.
+///
+/// Strings that are not valid versions sort before valid ones.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parse =
+        |v: &str| semver::Version::parse(v.trim_start_matches('v')).ok();
+
+    match (parse(a), parse(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (a, b) => a.is_some().cmp(&b.is_some()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compatibility() {
+        let binary = FirmwareBinary {
+            file: String::new(),
+            min: "v0.1.0".to_string(),
+            hardware: vec!["v1.0.0".to_string()],
+            devices: vec!["FD".to_string()],
+            notes: None,
+        };
+        let rev_a = Version {
+            major: 1,
+            minor: 0,
+            patch: 0,
+        };
+        let rev_b = Version {
+            major: 1,
+            minor: 1,
+            patch: 0,
+        };
+
+        assert!(binary
+            .check_compatible(DeviceIdentifier::CanFd, &rev_a)
+            .is_ok());
+        assert!(binary
+            .check_compatible(DeviceIdentifier::Serial, &rev_a)
+            .is_err());
+        assert!(binary
+            .check_compatible(DeviceIdentifier::CanFd, &rev_b)
+            .is_err());
+    }
+
+    #[test]
+    fn schema_dispatch() {
+        let manifest = br#"{
+            "schema": "v0.1.3",
+            "latest": "v0.3.0",
+            "stable": "v0.2.0",
+            "binaries": {
+                "v0.2.0": { "file": "https://example.com", "min": "v0.1.0" }
+            }
+        }"#;
+        assert!(Manifest::from_slice(manifest).is_ok());
+
+        let err = Manifest::from_slice(br#"{ "schema": "v0.2.0" }"#)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("upgrade the CLI"));
+    }
+
+    #[test]
+    fn update_available() {
+        let manifest = Manifest::from_slice(
+            br#"{
+                "schema": "v0.1.0",
+                "latest": "v0.3.0",
+                "stable": "v0.2.0",
+                "binaries": {}
+            }"#,
+        )
+        .unwrap();
+        let current = Version {
+            major: 0,
+            minor: 2,
+            patch: 0,
+        };
+
+        assert_eq!(
+            manifest
+                .update_available(&Channel::Stable, &current)
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            manifest
+                .update_available(&Channel::Latest, &current)
+                .unwrap(),
+            Some("v0.3.0")
+        );
+    }
+
+    #[test]
+    fn version_order() {
+        assert_eq!(compare_versions("v0.2.0", "v0.10.0"), Ordering::Less);
+        assert_eq!(
+            compare_versions("v1.0.0", "v1.0.0-rc.1"),
+            Ordering::Greater
+        );
+    }
 }
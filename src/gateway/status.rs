@@ -1,34 +1,123 @@
-use crate::write_with_header;
-use colored::Colorize;
-use std::{net::IpAddr, time::Instant};
-
-pub async fn command(
-    mut output: impl std::io::Write,
-    ip: IpAddr,
-) -> anyhow::Result<()> {
-    let mut client = super::client::Client::connect(ip).await?;
-
-    let start = Instant::now();
-
-    write_with_header(
-        &mut output,
-        "Serial".green(),
-        &format!("{}", client.serial().await??),
-    );
-
-    write_with_header(
-        &mut output,
-        "Hardware Version".green(),
-        &format!("{}", client.hardware_version().await??),
-    );
-
-    write_with_header(
-        &mut output,
-        "Firmware Version".green(),
-        &format!("{}", client.firmware_version().await??,),
-    );
-
-    writeln!(output, "Got status in {:?}", start.elapsed())?;
-
-    Ok(())
+//! Gateway status.
+
+use super::client::{Client, DeviceIdentifier, Serial, Version};
+use serde::{Serialize, Serializer};
+use std::{fmt::Display, net::Ipv4Addr};
+
+/// Everything the gateway reports about itself.
+#[derive(Debug, Serialize)]
+pub struct Status {
+    #[serde(rename = "device_type", serialize_with = "code")]
+    pub device: DeviceIdentifier,
+    #[serde(serialize_with = "display")]
+    pub serial: Serial,
+    #[serde(serialize_with = "display")]
+    pub hardware_version: Version,
+    #[serde(serialize_with = "display")]
+    pub firmware_version: Version,
+    pub dhcp: bool,
+    pub ipv4_address: Ipv4Addr,
+    /// Only present on devices with a CAN interface.
+    pub can: Option<CanStatus>,
+}
+
+/// CAN bus settings and counters.
+#[derive(Debug, Serialize)]
+pub struct CanStatus {
+    pub bitrate_nominal: u32,
+    pub bitrate_data: u32,
+    pub receive_errors: u16,
+    pub transmit_errors: u16,
+}
+
+impl Status {
+    /// Read the status using the client getters.
+    pub async fn read(client: &mut Client) -> crate::error::Result<Self> {
+        let device = client.device_identifier().await?;
+
+        let can = match device {
+            DeviceIdentifier::CanFd => Some(CanStatus {
+                bitrate_nominal: client.canbus_bitrate_nominal().await?,
+                bitrate_data: client.canbus_bitrate_data().await?,
+                receive_errors: client.canbus_receive_error_count().await?,
+                transmit_errors: client.canbus_transmit_error_count().await?,
+            }),
+            _ => None,
+        };
+
+        Ok(Self {
+            device,
+            serial: client.serial().await?,
+            hardware_version: client.hardware_version().await?,
+            firmware_version: client.firmware_version().await?,
+            dhcp: client.dhcp().await?,
+            ipv4_address: client.ipv4_address().await?,
+            can,
+        })
+    }
+
+    /// Status values as key, label and formatted value.
+    pub fn fields(&self) -> Vec<(&'static str, &'static str, String)> {
+        let mut fields = vec![
+            (
+                "device_type",
+                "Device Type",
+                format!("{} ({})", self.device.name(), self.device.code()),
+            ),
+            ("serial", "Serial", self.serial.to_string()),
+            (
+                "hardware_version",
+                "Hardware Version",
+                self.hardware_version.to_string(),
+            ),
+            (
+                "firmware_version",
+                "Firmware Version",
+                self.firmware_version.to_string(),
+            ),
+            (
+                "dhcp",
+                "DHCP",
+                if self.dhcp { "enabled" } else { "disabled" }.to_string(),
+            ),
+            (
+                "ipv4_address",
+                "IPv4 Address",
+                self.ipv4_address.to_string(),
+            ),
+        ];
+
+        if let Some(can) = &self.can {
+            fields.extend([
+                (
+                    "bitrate_nominal",
+                    "Nominal Bitrate",
+                    format!("{} bit/s", can.bitrate_nominal),
+                ),
+                (
+                    "bitrate_data",
+                    "Data Bitrate",
+                    format!("{} bit/s", can.bitrate_data),
+                ),
+                ("rx_errors", "RX Errors", can.receive_errors.to_string()),
+                ("tx_errors", "TX Errors", can.transmit_errors.to_string()),
+            ]);
+        }
+
+        fields
+    }
+}
+
+fn display<S: Serializer>(
+    value: &impl Display,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+fn code<S: Serializer>(
+    device: &DeviceIdentifier,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&device.code())
 }
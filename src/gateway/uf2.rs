@@ -0,0 +1,241 @@
+//! Firmware upload over the network.
+//!
+//! Gateways accept UF2 firmware files on a TCP port, one 512 byte block at a
+//! time.
+
+use crate::{error::Error, gateway::client::DeviceIdentifier};
+use std::io::Cursor;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader, ErrorKind};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tracing::{debug, instrument, trace};
+use uftwo::{Block, Flags};
+
+/// UF2 family ID of the STM32H7 microcontroller in all gateways.
+const FAMILY_STM32H7: u32 = 0x6db6_6082;
+
+/// TCP port of the UF2 endpoint.
+pub const PORT: u16 = 21830;
+
+/// How long the gateway may take to erase its flash and accept the first
+/// block.
+const ERASE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Upload a UF2 firmware file to the gateway.
+///
+/// `progress` is called with the block number and total number of blocks
+/// before each block is sent. The gateway erases its flash on the first
+/// block.
+///
+/// `io_timeout` applies to connecting and to sending each block and reading
+/// its response.
+#[instrument(level = "debug", skip(binary, progress), fields(len = binary.len()))]
+pub async fn upload(
+    ip: IpAddr,
+    io_timeout: Duration,
+    binary: &[u8],
+    mut progress: impl FnMut(u32, u32),
+) -> Result<(), Error> {
+    if !binary.len().is_multiple_of(512) {
+        return Err(misaligned());
+    }
+
+    // setup reader
+    let cursor = Cursor::new(binary);
+    let mut uf2 = BufReader::new(cursor);
+
+    // open TCP connection to UF2 endpoint
+    let addr = SocketAddr::new(ip, PORT);
+    let mut stream = timeout(io_timeout, TcpStream::connect(addr))
+        .await
+        .map_err(|_| {
+            Error::Timeout(format!("Timed out connecting to {}.", addr))
+        })?
+        .map_err(|err| {
+            Error::Unreachable(format!(
+                "Could not connect to {}: {}.",
+                addr, err
+            ))
+        })?;
+
+    let mut block_buf = [0; 512];
+
+    loop {
+        match uf2.read(&mut block_buf).await {
+            Ok(0) => break,
+            Ok(n) => {
+                if n != 512 {
+                    return Err(misaligned());
+                }
+
+                let block = match Block::from_bytes(&block_buf) {
+                    Ok(b) => b,
+                    Err(_err) => return Err(invalid_block()),
+                };
+
+                debug!(
+                    block = block.block,
+                    total_blocks = block.total_blocks,
+                    target_addr = format_args!("{:#010x}", block.target_addr),
+                    "sending block"
+                );
+
+                progress(block.block, block.total_blocks);
+
+                let wait = match block.block {
+                    0 => ERASE_TIMEOUT.max(io_timeout),
+                    _ => io_timeout,
+                };
+                let response =
+                    send_block(&mut stream, addr, &block_buf, wait).await?;
+                trace!(block = block.block, ?response, "block response");
+
+                if &response == b"ok\0" {
+                    continue;
+                } else {
+                    debug!(block = block.block, ?response, "block rejected");
+                    return Err(Error::Firmware(format!(
+                        "Gateway rejected block {}. Please reset the device and try again.",
+                        block.block
+                    )));
+                }
+            }
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(_err) => return Err(invalid_block()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Send a block and read the gateway's response, each within the timeout.
+async fn send_block(
+    stream: &mut TcpStream,
+    addr: SocketAddr,
+    block: &[u8],
+    wait: Duration,
+) -> Result<[u8; 3], Error> {
+    let timed_out =
+        |_| Error::Timeout(format!("Timed out sending firmware to {}.", addr));
+    let lost = |err: std::io::Error| {
+        Error::Unreachable(format!("Lost connection to {}: {}.", addr, err))
+    };
+
+    timeout(wait, stream.write_all(block))
+        .await
+        .map_err(timed_out)?
+        .map_err(lost)?;
+
+    let mut response = [0; 3];
+    timeout(wait, stream.read_exact(&mut response))
+        .await
+        .map_err(timed_out)?
+        .map_err(lost)?;
+
+    Ok(response)
+}
+
+fn misaligned() -> Error {
+    Error::Firmware(
+        "Failed to read firmware file: firmware file did not align to 512 byte block."
+            .to_string(),
+    )
+}
+
+fn invalid_block() -> Error {
+    Error::Firmware("Failed to read firmware file block.".to_string())
+}
+
+/// UF2 family IDs of firmware for a device type, empty if not known.
+pub fn family_ids(device: DeviceIdentifier) -> &'static [u32] {
+    match device {
+        DeviceIdentifier::CanFd | DeviceIdentifier::Serial => &[FAMILY_STM32H7],
+        DeviceIdentifier::Unknown(_) => &[],
+    }
+}
+
+/// Read the UF2 family ID from the first block of a firmware file.
+pub fn family_id(binary: &[u8]) -> Option<u32> {
+    let block = Block::from_bytes(binary.get(..512)?).ok()?;
+
+    if block.flags.contains(Flags::FamilyId) {
+        Some(block.board_family_id_or_file_size)
+    } else {
+        None
+    }
+}
+
+/// Check the family ID of a firmware file matches a device type.
+///
+/// The family ID only identifies the microcontroller, so this can't tell
+/// firmware for device types with the same microcontroller apart.
+pub fn check_family(
+    binary: &[u8],
+    device: DeviceIdentifier,
+) -> Result<(), Error> {
+    let Some(id) = family_id(binary) else {
+        return Err(Error::Unsupported(
+            "Firmware file does not specify a family ID, so it can't be checked."
+                .to_string(),
+        ));
+    };
+
+    match family_ids(device) {
+        [] => Err(Error::Unsupported(format!(
+            "Family IDs of {} devices are not known, so the firmware file can't be checked.",
+            device.code()
+        ))),
+        ids if ids.contains(&id) => Ok(()),
+        _ => Err(Error::Unsupported(format!(
+            "Firmware file family ID {:#010x} is not for {} devices.",
+            id,
+            device.code()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// First block of a UF2 file, with a family ID if given.
+    fn block(family_id: Option<u32>) -> Vec<u8> {
+        let mut block = vec![0; 512];
+        block[0..4].copy_from_slice(&0x0A32_4655_u32.to_le_bytes());
+        block[4..8].copy_from_slice(&0x9E5D_5157_u32.to_le_bytes());
+        if let Some(id) = family_id {
+            block[8..12].copy_from_slice(&Flags::FamilyId.bits().to_le_bytes());
+            block[28..32].copy_from_slice(&id.to_le_bytes());
+        }
+        block[508..512].copy_from_slice(&0x0AB1_6F30_u32.to_le_bytes());
+        block
+    }
+
+    #[test]
+    fn family() {
+        assert_eq!(
+            family_id(&block(Some(FAMILY_STM32H7))),
+            Some(FAMILY_STM32H7)
+        );
+        assert_eq!(family_id(&block(None)), None);
+        assert_eq!(family_id(&[0; 512]), None);
+        assert_eq!(family_id(&[]), None);
+    }
+
+    #[test]
+    fn check() {
+        let h7 = block(Some(FAMILY_STM32H7));
+        assert!(check_family(&h7, DeviceIdentifier::CanFd).is_ok());
+        assert!(check_family(&h7, DeviceIdentifier::Serial).is_ok());
+        assert!(matches!(
+            check_family(&block(Some(0xe48b_ff56)), DeviceIdentifier::CanFd),
+            Err(Error::Unsupported(_))
+        ));
+        // files that can't be checked are refused.
+        assert!(check_family(&block(None), DeviceIdentifier::CanFd).is_err());
+        assert!(check_family(&h7, DeviceIdentifier::Unknown(9)).is_err());
+    }
+}
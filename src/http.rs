@@ -1,7 +1,36 @@
-use reqwest::{Client, Error};
+//! HTTP helpers.
+
+use reqwest::{Client, Error, Response};
+use tracing::debug;
 
 pub fn client() -> Result<Client, Error> {
     Client::builder()
         .user_agent(format!("umi-cli/{}", env!("CARGO_PKG_VERSION")))
         .build()
 }
+
+/// Send a GET request, failing on error statuses.
+#[tracing::instrument(level = "debug")]
+pub async fn get(url: &str) -> Result<Response, Error> {
+    let response = client()?.get(url).send().await?;
+    debug!(status = %response.status(), "response");
+    response.error_for_status()
+}
+
+/// Get the body of a URL.
+pub async fn get_bytes(url: &str) -> Result<Vec<u8>, Error> {
+    Ok(get(url).await?.bytes().await?.to_vec())
+}
+
+/// Describe an error followed by its underlying causes.
+pub fn describe(err: &reqwest::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = std::error::Error::source(err);
+
+    while let Some(err) = source {
+        message = format!("{}: {}", message, err);
+        source = err.source();
+    }
+
+    message
+}
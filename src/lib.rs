@@ -0,0 +1,31 @@
+//! UMI device management.
+//!
+//! Library behind the `umi` command line tool, for managing Gateway and
+//! Adapter devices from Rust.
+//!
+//! - [`gateway::client`] talks Modbus to a Gateway over TCP or serial.
+//! - [`gateway::manifest`] lists published Gateway firmware.
+//! - [`gateway::uf2`] flashes Gateway firmware over the network.
+//! - [`adapter`] finds attached Adapters, [`adapter::dfu`] flashes them.
+//!
+//! ```no_run
+//! use umi::gateway::client::{Client, Options, Transport};
+//!
+//! # async fn example() -> umi::error::Result<()> {
+//! let transport = Transport::Tcp("192.168.1.10:502".parse().unwrap());
+//! let mut client = Client::connect(transport, 1, Options::default()).await?;
+//!
+//! let errors = client.canbus_receive_error_count().await?;
+//! println!("{} receive errors", errors);
+//!
+//! client.restart().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod adapter;
+pub mod channel;
+pub mod error;
+pub mod gateway;
+#[doc(hidden)]
+pub mod http;
@@ -1,9 +1,12 @@
-mod adapter;
-mod gateway;
-mod http;
+mod cli;
 
 use clap::{Parser, Subcommand};
+use cli::{
+    adapter, gateway, inventory, logging, provision, self_update, serve, tui,
+};
 use colored::ColoredString;
+use std::process::ExitCode;
+use umi::error::Error;
 
 #[derive(Subcommand)]
 pub enum Commands {
@@ -11,25 +14,68 @@ pub enum Commands {
     Gateway(gateway::Cmd),
     /// Commands for managing Adapter devices
     Adapter(adapter::Cmd),
+    /// Commands for browsing the local device inventory
+    Inventory(inventory::Cmd),
+    /// Provision a Gateway from a profile for end-of-line setup
+    Provision(provision::Cmd),
+    /// Serve a local HTTP API for managing devices
+    Serve(serve::Cmd),
+    /// Interactive dashboard of gateways and Adapters
+    Tui(tui::Cmd),
+    /// Commands for managing this CLI
+    #[command(name = "self")]
+    SelfUpdate(self_update::Cmd),
 }
 
 #[derive(Parser)]
-#[command(about, version)]
+#[command(
+    about,
+    version,
+    after_help = "Exit codes: 0 success, 1 other error, 2 invalid input, \
+                  3 device unreachable, 4 timeout, 5 Modbus exception, \
+                  6 unsupported by device, 7 firmware error."
+)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    #[command(flatten)]
+    log: logging::LogOptions,
 }
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> ExitCode {
     let args = Cli::parse();
 
+    match run(args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            ExitCode::from(exit_code(&err))
+        }
+    }
+}
+
+async fn run(args: Cli) -> anyhow::Result<()> {
+    args.log.init()?;
+
     match args.command {
         Commands::Gateway(command) => command.run().await,
         Commands::Adapter(command) => command.run().await,
+        Commands::Inventory(command) => command.run().await,
+        Commands::Provision(command) => command.run().await,
+        Commands::Serve(command) => command.run().await,
+        Commands::Tui(command) => command.run().await,
+        Commands::SelfUpdate(command) => command.run().await,
     }
 }
 
+/// Exit code for any error returned by a command.
+fn exit_code(err: &anyhow::Error) -> u8 {
+    err.chain()
+        .find_map(|err| err.downcast_ref::<Error>())
+        .map_or(1, Error::exit_code)
+}
+
 fn write_with_header(
     mut output: impl std::io::Write,
     header: ColoredString,
@@ -47,3 +93,19 @@ fn write_with_header(
         let _ = writeln!(output, "            {line}");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes() {
+        let err = anyhow::Error::from(Error::Timeout("timed out".to_string()));
+        assert_eq!(exit_code(&err), 4);
+
+        let err = err.context("Could not read status.");
+        assert_eq!(exit_code(&err), 4);
+
+        assert_eq!(exit_code(&anyhow::Error::msg("other")), 1);
+    }
+}